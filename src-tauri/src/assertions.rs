@@ -0,0 +1,331 @@
+use crate::models::*;
+use regex::Regex;
+use serde_json::Value;
+
+/// Evaluate every assertion against a response, in order
+pub fn evaluate(assertions: &[Assertion], response: &HttpResponse) -> Vec<AssertionResult> {
+    let body = body_text(response);
+    let json: Option<Value> = serde_json::from_str(body).ok();
+
+    assertions
+        .iter()
+        .map(|assertion| {
+            let (passed, message) = check(assertion, response, body, json.as_ref());
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Text the body assertions run against; streamed files only expose their preview
+fn body_text(response: &HttpResponse) -> &str {
+    match &response.body {
+        ResponseBody::Text { content } | ResponseBody::Truncated { content, .. } => content,
+        ResponseBody::File { preview, .. } => preview,
+        ResponseBody::Binary { .. } => "",
+    }
+}
+
+fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+    response
+        .headers
+        .iter()
+        .find(|h| h.key.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+fn check(assertion: &Assertion, response: &HttpResponse, body: &str, json: Option<&Value>) -> (bool, String) {
+    match assertion {
+        Assertion::StatusEquals { status } => (
+            response.status == *status,
+            format!("Expected status {}, got {}", status, response.status),
+        ),
+        Assertion::StatusInRange { min, max } => (
+            (*min..=*max).contains(&response.status),
+            format!("Expected status in {}-{}, got {}", min, max, response.status),
+        ),
+        Assertion::HeaderExists { name } => match header(response, name) {
+            Some(_) => (true, format!("Header {} is present", name)),
+            None => (false, format!("Header {} is missing", name)),
+        },
+        Assertion::HeaderMatches { name, pattern } => {
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(e) => return (false, format!("Invalid pattern {}: {}", pattern, e)),
+            };
+            match header(response, name) {
+                Some(value) => (
+                    regex.is_match(value),
+                    format!("Expected header {} to match {}, got {}", name, pattern, value),
+                ),
+                None => (false, format!("Header {} is missing", name)),
+            }
+        }
+        Assertion::JsonPathEquals { path, value } => {
+            let Some(json) = json else {
+                return (false, "Response body is not JSON".to_string());
+            };
+            match json_path(json, path) {
+                Ok(Some(actual)) => (
+                    actual == value,
+                    format!("Expected {} to equal {}, got {}", path, value, actual),
+                ),
+                Ok(None) => (false, format!("{} not found in response", path)),
+                Err(message) => (false, message),
+            }
+        }
+        Assertion::BodyContains { text } => (
+            body.contains(text.as_str()),
+            format!("Expected body to contain {:?}", text),
+        ),
+        Assertion::ResponseTimeBelow { max_ms } => (
+            response.time_ms < *max_ms as u128,
+            format!("Expected response time below {}ms, took {}ms", max_ms, response.time_ms),
+        ),
+        Assertion::JsonSchema { schema } => {
+            let Some(json) = json else {
+                return (false, "Response body is not JSON".to_string());
+            };
+            let validator = match jsonschema::validator_for(schema) {
+                Ok(validator) => validator,
+                Err(e) => return (false, format!("Invalid schema: {}", e)),
+            };
+            let errors: Vec<String> = validator
+                .iter_errors(json)
+                .map(|e| match e.instance_path.to_string() {
+                    path if path.is_empty() => e.to_string(),
+                    path => format!("{} at {}", e, path),
+                })
+                .collect();
+            if errors.is_empty() {
+                (true, "Body matches schema".to_string())
+            } else {
+                (false, errors.join("; "))
+            }
+        }
+    }
+}
+
+/// Resolve a JSONPath subset: `$`, `.name`, `['name']` and `[index]` steps.
+/// The leading `$` is optional, so `data.id` works too.
+fn json_path<'a>(root: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    let invalid = || format!("Invalid JSON path: {}", path);
+    let trimmed = path.trim();
+    let normalized = match trimmed.strip_prefix('$') {
+        Some(rest) => rest.to_string(),
+        None if trimmed.starts_with('[') => trimmed.to_string(),
+        None => format!(".{}", trimmed),
+    };
+
+    let mut rest = normalized.as_str();
+    let mut current = root;
+
+    while !rest.is_empty() {
+        let step;
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            step = Step::Key(&after[..end]);
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let inner = after[..end].trim();
+            step = match inner.strip_prefix(['\'', '"']).and_then(|s| s.strip_suffix(['\'', '"'])) {
+                Some(key) => Step::Key(key),
+                None => Step::Index(inner.parse().map_err(|_| invalid())?),
+            };
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+
+        current = match (step, current) {
+            (Step::Key(key), Value::Object(map)) => match map.get(key) {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            (Step::Index(index), Value::Array(items)) => match items.get(index) {
+                Some(value) => value,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+    }
+
+    Ok(Some(current))
+}
+
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            status_text: String::new(),
+            headers: headers
+                .iter()
+                .map(|(key, value)| KeyValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    enabled: true,
+                })
+                .collect(),
+            body: ResponseBody::Text { content: body.to_string() },
+            size_bytes: body.len(),
+            time_ms: 120,
+            timing: TimingBreakdown::default(),
+            content_type: None,
+            content_encoding: None,
+        }
+    }
+
+    fn passes(assertion: Assertion, response: &HttpResponse) -> bool {
+        evaluate(&[assertion], response)[0].passed
+    }
+
+    fn sample() -> Value {
+        json!({ "data": { "items": [{ "id": 7 }, { "id": 8 }], "odd key": true } })
+    }
+
+    #[test]
+    fn json_path_root() {
+        let root = sample();
+        assert_eq!(json_path(&root, "$").unwrap(), Some(&root));
+    }
+
+    #[test]
+    fn json_path_dot_keys() {
+        let root = sample();
+        assert_eq!(json_path(&root, "$.data.items[1].id").unwrap(), Some(&json!(8)));
+        // The leading $ is optional
+        assert_eq!(json_path(&root, "data.items[0].id").unwrap(), Some(&json!(7)));
+    }
+
+    #[test]
+    fn json_path_bracket_keys() {
+        let root = sample();
+        assert_eq!(json_path(&root, "$['data']['odd key']").unwrap(), Some(&json!(true)));
+        assert_eq!(json_path(&root, r#"$["data"].items"#).unwrap().map(|v| v.is_array()), Some(true));
+    }
+
+    #[test]
+    fn json_path_indexes() {
+        let root = json!([[1, 2], [3]]);
+        assert_eq!(json_path(&root, "$[0][1]").unwrap(), Some(&json!(2)));
+        assert_eq!(json_path(&root, "[1][0]").unwrap(), Some(&json!(3)));
+    }
+
+    #[test]
+    fn json_path_missing_is_none() {
+        let root = sample();
+        assert_eq!(json_path(&root, "$.data.missing").unwrap(), None);
+        assert_eq!(json_path(&root, "$.data.items[5]").unwrap(), None);
+        // Indexing an object or keying an array finds nothing
+        assert_eq!(json_path(&root, "$.data[0]").unwrap(), None);
+        assert_eq!(json_path(&root, "$.data.items.id").unwrap(), None);
+    }
+
+    #[test]
+    fn json_path_invalid_is_error() {
+        let root = sample();
+        for path in ["$..data", "$.data[", "$.data[x]", "$data", "$.data.items[-1]"] {
+            assert!(json_path(&root, path).is_err(), "{} should be invalid", path);
+        }
+    }
+
+    #[test]
+    fn status_equals() {
+        let ok = response(200, &[], "");
+        assert!(passes(Assertion::StatusEquals { status: 200 }, &ok));
+        assert!(!passes(Assertion::StatusEquals { status: 201 }, &ok));
+    }
+
+    #[test]
+    fn status_in_range_is_inclusive() {
+        assert!(passes(Assertion::StatusInRange { min: 200, max: 299 }, &response(299, &[], "")));
+        assert!(!passes(Assertion::StatusInRange { min: 200, max: 299 }, &response(300, &[], "")));
+    }
+
+    #[test]
+    fn header_exists_ignores_case() {
+        let response = response(200, &[("Content-Type", "application/json")], "");
+        assert!(passes(Assertion::HeaderExists { name: "content-type".to_string() }, &response));
+        assert!(!passes(Assertion::HeaderExists { name: "etag".to_string() }, &response));
+    }
+
+    #[test]
+    fn header_matches() {
+        let response = response(200, &[("Cache-Control", "max-age=60")], "");
+        let matches = |pattern: &str| {
+            evaluate(
+                &[Assertion::HeaderMatches { name: "cache-control".to_string(), pattern: pattern.to_string() }],
+                &response,
+            )
+            .remove(0)
+        };
+        assert!(matches(r"max-age=\d+").passed);
+        assert!(!matches("no-store").passed);
+        let invalid = matches("(");
+        assert!(!invalid.passed);
+        assert!(invalid.message.starts_with("Invalid pattern"));
+    }
+
+    #[test]
+    fn json_path_equals() {
+        let body = sample().to_string();
+        let response = response(200, &[], &body);
+        let equals = |path: &str, value: Value| {
+            passes(Assertion::JsonPathEquals { path: path.to_string(), value }, &response)
+        };
+        assert!(equals("$.data.items[0].id", json!(7)));
+        assert!(!equals("$.data.items[0].id", json!("7")));
+        assert!(!equals("$.data.missing", Value::Null));
+
+        let not_json = self::response(200, &[], "<html>");
+        let result = evaluate(&[Assertion::JsonPathEquals { path: "$".to_string(), value: Value::Null }], &not_json);
+        assert_eq!(result[0].message, "Response body is not JSON");
+    }
+
+    #[test]
+    fn body_contains() {
+        let response = response(200, &[], "hello world");
+        assert!(passes(Assertion::BodyContains { text: "o w".to_string() }, &response));
+        assert!(!passes(Assertion::BodyContains { text: "World".to_string() }, &response));
+    }
+
+    #[test]
+    fn response_time_below() {
+        let response = response(200, &[], "");
+        assert!(passes(Assertion::ResponseTimeBelow { max_ms: 121 }, &response));
+        assert!(!passes(Assertion::ResponseTimeBelow { max_ms: 120 }, &response));
+    }
+
+    #[test]
+    fn json_schema() {
+        let response = response(200, &[], r#"{"id": "seven"}"#);
+        let schema = json!({
+            "type": "object",
+            "properties": { "id": { "type": "integer" } },
+            "required": ["id"]
+        });
+        let result = evaluate(&[Assertion::JsonSchema { schema }], &response).remove(0);
+        assert!(!result.passed);
+        assert!(result.message.ends_with("at /id"));
+
+        let loose = json!({ "type": "object", "required": ["id"] });
+        assert!(passes(Assertion::JsonSchema { schema: loose }, &response));
+        assert!(!passes(Assertion::JsonSchema { schema: json!({ "type": 5 }) }, &response));
+    }
+}
@@ -1,131 +1,498 @@
-use crate::{db, http, models::*};
+use crate::{
+    assertions, cookies::CookieJar, db, graphql, http, models::*, oauth, redact, replay, runner, scripting, secrets,
+    template, trend,
+};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::ShellExt;
 
-/// Send HTTP request with variable substitution
+/// Send HTTP request with variable substitution. `sendId` identifies the
+/// send for progress events and `cancel_request`.
 #[allow(non_snake_case)]
 #[tauri::command]
-pub async fn send_request(mut config: RequestConfig, workspaceId: String) -> SendResult {
-    // 1. Fetch active variables for this workspace
-    let variables = match db::get_active_variables(&workspaceId).await {
-        Ok(v) => v,
-        Err(e) => {
-            return SendResult::Failed { 
-                request_id: "error".to_string(), 
-                error: HttpError::Network { message: format!("Failed to fetch variables: {}", e) }
-            };
+pub async fn send_request(
+    app: AppHandle,
+    config: RequestConfig,
+    workspaceId: String,
+    sendId: Option<String>,
+    savedRequestId: Option<String>,
+) -> SendResult {
+    send_with_locals(app, config, workspaceId, sendId, savedRequestId, Vec::new()).await
+}
+
+/// Send a request with request-local variables (e.g. the current data row of
+/// a collection run) taking precedence over every stored scope
+#[allow(non_snake_case)]
+pub async fn send_with_locals(
+    app: AppHandle,
+    config: RequestConfig,
+    workspaceId: String,
+    sendId: Option<String>,
+    savedRequestId: Option<String>,
+    locals: Vec<(String, String)>,
+) -> SendResult {
+    send_and_record(app, config, workspaceId, sendId, savedRequestId, locals).await.0
+}
+
+/// What the steps before sending gathered, kept when one of them fails so
+/// the failure can still be recorded
+#[derive(Default)]
+struct SendState {
+    variables: Vec<(String, String)>,
+    script_reports: Vec<ScriptReport>,
+    history_secrets: Vec<String>,  // Values that must never reach the history table
+    substituted: bool,  // The request holds variable values
+}
+
+/// Steps 1-4 of a send: resolve variables, run the pre-request script,
+/// substitute variables into `config`, then load the cookie jar and the
+/// directory for response files
+async fn prepare_send(
+    app: &AppHandle,
+    config: &mut RequestConfig,
+    workspace_id: &str,
+    saved_request_id: Option<&str>,
+    locals: Vec<(String, String)>,
+    state: &mut SendState,
+) -> Result<(CookieJar, PathBuf), HttpError> {
+    // 1. Fetch the variables visible to this request, by scope precedence,
+    // and which of their values are secret
+    let fetch_error = |e: String| HttpError::Network { message: format!("Failed to fetch variables: {}", e) };
+    let location = variable_location(saved_request_id)
+        .await
+        .map_err(|e| fetch_error(e.to_string()))?;
+    state.variables = db::get_active_variables(workspace_id, &location)
+        .await
+        .map_err(|e| fetch_error(e.to_string()))?;
+    state.history_secrets = db::get_active_secret_values(workspace_id, &location)
+        .await
+        .map_err(|e| fetch_error(e.to_string()))?;
+    for (key, value) in locals {
+        set_variable(&mut state.variables, key, value);
+    }
+
+    // 2. Run the pre-request script, which may edit the request and set variables
+    if !scripting::is_empty(&config.scripts.pre_request) {
+        let script = config.scripts.pre_request.clone();
+        let (updated, run) = scripting::run_pre_request(script, config.clone(), state.variables.clone()).await;
+        *config = updated;
+        let error = run.report.error.clone();
+        state.script_reports.push(run.report);
+        if let Some(message) = error {
+            return Err(HttpError::Script { message });
         }
-    };
+        let script_secrets = apply_script_variables(workspace_id, &run.variables_set, &mut state.variables).await;
+        state.history_secrets.extend(script_secrets);
+    }
 
-    // 2. Perform substitution in URL, headers, params, and body
-    substitute_variables(&mut config, &variables);
+    // 3. Perform substitution in URL, headers, params, and body
+    template::substitute_request(config, &state.variables).map_err(|message| HttpError::Template { message })?;
+    if let Some(auth) = &config.auth {
+        state.history_secrets.extend(redact::auth_secrets(auth));
+    }
+    state.substituted = true;
 
-    // 3. Execute request
-    let result = http::execute_request(config.clone()).await;
+    // 4. Load the workspace cookie jar
+    let cookies = db::get_cookies(workspace_id).await.map_err(|e| HttpError::Network {
+        message: format!("Failed to load cookies: {}", e),
+    })?;
+    let download_dir = response_files_dir(app).map_err(|message| HttpError::Network { message })?;
 
-    // 4. Save to history if successful
-    if let SendResult::Success { request_id: _, response } = &result {
-        // Prepare headers JSON
-        let req_headers = serde_json::to_string(&config.headers).unwrap_or_default();
-        
-        // Prepare params JSON
-        let req_params = serde_json::to_string(&config.params).unwrap_or_default();
-        
-        // Prepare body
-        let req_body = match &config.body {
-            RequestBody::None => None,
-            RequestBody::Raw { content, .. } => Some(content.clone()),
-            RequestBody::Json { value } => Some(value.to_string()),
-            RequestBody::FormUrlEncoded { fields } => Some(serde_json::to_string(fields).unwrap_or_default()),
-            RequestBody::Multipart { fields } => Some(serde_json::to_string(fields).unwrap_or_default()),
-        };
-
-        // Prepare response headers JSON
-        let res_headers = serde_json::to_string(&response.headers).unwrap_or_default();
-
-        // Prepare response body preview (first 1000 chars)
-        let (res_body_full, res_body_preview, res_body_type) = match &response.body {
-            ResponseBody::Text { content } => {
-                let preview: String = content.chars().take(1000).collect();
-                (Some(content.clone()), Some(preview), "text")
-            },
-            ResponseBody::Binary { preview_hex } => {
-                (None, Some(preview_hex.clone()), "binary")
-            },
-            ResponseBody::Truncated { content, .. } => {
-                let preview: String = content.chars().take(1000).collect();
-                (Some(content.clone()), Some(preview), "truncated")
+    Ok((CookieJar::new(workspace_id, cookies), download_dir))
+}
+
+/// Send a request and write it to history, whether it succeeded or not, also
+/// returning the redactor the history entry was written with
+#[allow(non_snake_case)]
+async fn send_and_record(
+    app: AppHandle,
+    mut config: RequestConfig,
+    workspaceId: String,
+    sendId: Option<String>,
+    savedRequestId: Option<String>,
+    locals: Vec<(String, String)>,
+) -> (SendResult, redact::Redactor) {
+    // The request as edited, before scripts and variable substitution
+    let edited = config.clone();
+
+    let mut state = SendState::default();
+    let prepared = prepare_send(&app, &mut config, &workspaceId, savedRequestId.as_deref(), locals, &mut state).await;
+
+    let mut result = match prepared {
+        Ok((mut jar, download_dir)) => {
+            // 5. Exchange OAuth 2.0 settings for a cached, refreshed or new access token
+            let mut auth_error = None;
+            if let Some(AuthConfig::OAuth2(oauth)) = config.auth.clone() {
+                let mut token_ctx = oauth::TokenContext {
+                    app: &app,
+                    workspace_id: &workspaceId,
+                    template: &config,
+                    jar: &mut jar,
+                    download_dir: &download_dir,
+                };
+                match oauth::access_token(&oauth, &mut token_ctx, false).await {
+                    Ok(token) => {
+                        state.history_secrets.push(token.access_token.clone());
+                        config.auth = Some(AuthConfig::Bearer { token: token.access_token });
+                    }
+                    Err(error) => auth_error = Some(error),
+                }
             }
-        };
-
-        if let Err(e) = db::add_history_entry(
-            &uuid::Uuid::new_v4().to_string(),
-            &workspaceId,
-            None, // Don't link to request table for ad-hoc requests
-            &config.method,
-            &config.url,
-            &req_headers,
-            &req_params,
-            req_body.as_deref(),
-            response.status,
-            &res_headers,
-            res_body_preview.as_deref(),
-            res_body_full.as_deref(),
-            res_body_type,
-            response.size_bytes as i64,
-            response.time_ms as i64,
-            response.content_type.as_deref(),
-            response.content_encoding.as_deref(),
-        ).await {
-            eprintln!("Failed to save history: {:?}", e);
+
+            // 6. Execute request, streaming progress events to the frontend
+            let result = match auth_error {
+                Some(error) => SendResult::Failed {
+                    send_id: sendId.unwrap_or_else(|| "error".to_string()),
+                    error,
+                    scripts: Vec::new(),
+                },
+                None => {
+                    let progress_app = app.clone();
+                    let ctx = http::SendContext {
+                        jar: &mut jar,
+                        download_dir,
+                        on_progress: Some(Arc::new(move |progress: RequestProgress| {
+                            let _ = progress_app.emit("request-progress", progress);
+                        })),
+                    };
+                    http::execute_request(config.clone(), sendId, ctx).await
+                }
+            };
+
+            // 7. Persist cookies set by the response
+            if let Err(e) = db::save_cookie_jar(&jar).await {
+                eprintln!("Failed to save cookies: {:?}", e);
+            }
+
+            result
+        }
+        Err(error) => SendResult::Failed {
+            send_id: sendId.unwrap_or_else(|| "error".to_string()),
+            error,
+            scripts: Vec::new(),
+        },
+    };
+
+    // 8. Run the post-response script, e.g. to extract a token into a variable
+    if let SendResult::Success { response, .. } = &result {
+        if !scripting::is_empty(&config.scripts.post_response) {
+            let run = scripting::run_post_response(
+                config.scripts.post_response.clone(),
+                config.clone(),
+                (**response).clone(),
+                state.variables.clone(),
+            )
+            .await;
+            let script_secrets = apply_script_variables(&workspaceId, &run.variables_set, &mut state.variables).await;
+            state.history_secrets.extend(script_secrets);
+            state.script_reports.push(run.report);
         }
     }
 
-    result
-}
+    // 9. Evaluate the request's assertions
+    if let SendResult::Success { response, assertions: results, .. } = &mut result {
+        *results = assertions::evaluate(&config.assertions, response);
+    }
 
-fn substitute_variables(config: &mut RequestConfig, variables: &[(String, String)]) {
-    for (key, value) in variables {
-        let pattern = format!("{{{{{}}}}}", key);
-        
-        // Replace in URL
-        config.url = config.url.replace(&pattern, value);
-        
-        // Replace in Headers
-        for header in &mut config.headers {
-            header.key = header.key.replace(&pattern, value);
-            header.value = header.value.replace(&pattern, value);
+    // Report the scripts that ran, including a pre-request script that failed
+    match &mut result {
+        SendResult::Success { scripts, .. } | SendResult::Failed { scripts, .. } => {
+            *scripts = std::mem::take(&mut state.script_reports);
         }
-        
-        // Replace in Params
-        for param in &mut config.params {
-            param.key = param.key.replace(&pattern, value);
-            param.value = param.value.replace(&pattern, value);
+    }
+
+    // 10. Save to history, with credentials redacted. Failed sends keep the
+    // request and the error so timeouts, TLS errors etc. can be looked at later.
+    let redact_names = db::get_setting("history_redact_names")
+        .await
+        .ok()
+        .flatten()
+        .map(|s| s.value.split(',').map(|n| n.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let redactor = redact::Redactor::new(state.history_secrets, &redact_names);
+    // A send that stopped before substitution records the request as edited
+    let config = redactor.redact_request(if state.substituted { &config } else { &edited });
+
+    // Prepare headers JSON
+    let req_headers = serde_json::to_string(&config.headers).unwrap_or_default();
+    
+    // Prepare params JSON
+    let req_params = serde_json::to_string(&config.params).unwrap_or_default();
+    
+    // Prepare body
+    let req_body = match &config.body {
+        RequestBody::None => None,
+        RequestBody::Raw { content, .. } => Some(content.clone()),
+        RequestBody::Json { value } => Some(value.to_string()),
+        RequestBody::FormUrlEncoded { fields } => Some(serde_json::to_string(fields).unwrap_or_default()),
+        RequestBody::Multipart { fields } => Some(serde_json::to_string(fields).unwrap_or_default()),
+        RequestBody::File { path, .. } => Some(path.clone()),
+        RequestBody::GraphQL { query, variables, operation_name } => Some(
+            graphql::request_payload(query, variables.as_ref(), operation_name.as_deref()).to_string(),
+        ),
+    };
+
+    // Store the request as edited, so a replay runs its scripts and resolves
+    // its variables again instead of sending redacted values
+    let req_config = serde_json::to_string(&redactor.redact_request(&edited)).ok();
+
+    let history_id = uuid::Uuid::new_v4().to_string();
+    let saved = match &result {
+        SendResult::Success { response, assertions, .. } => {
+            let response_headers = redactor.redact_pairs(&response.headers);
+
+            // Prepare response headers JSON
+            let res_headers = serde_json::to_string(&response_headers).unwrap_or_default();
+
+            // Prepare timing breakdown JSON
+            let res_timing = serde_json::to_string(&response.timing).ok();
+
+            // Prepare assertion results JSON; messages may quote response values
+            let assertion_results = (!assertions.is_empty())
+                .then(|| redactor.redact_text(&serde_json::to_string(assertions).unwrap_or_default()));
+
+            // Prepare response body preview (first 1000 chars)
+            let (res_body_full, res_body_preview, res_body_type, res_file) = match &response.body {
+                ResponseBody::Text { content } => {
+                    let content = redactor.redact_text(content);
+                    let preview: String = content.chars().take(1000).collect();
+                    (Some(content), Some(preview), "text", None)
+                },
+                ResponseBody::Binary { preview_hex } => {
+                    (None, Some(preview_hex.clone()), "binary", None)
+                },
+                ResponseBody::Truncated { content, .. } => {
+                    let content = redactor.redact_text(content);
+                    let preview: String = content.chars().take(1000).collect();
+                    (Some(content), Some(preview), "truncated", None)
+                }
+                ResponseBody::File { path, preview, .. } => {
+                    // The file is deleted along with the history entry
+                    let preview: String = redactor.redact_text(preview).chars().take(1000).collect();
+                    (None, Some(preview), "file", Some(path.as_str()))
+                }
+            };
+
+            db::add_history_entry(
+                &history_id,
+                &workspaceId,
+                savedRequestId.as_deref(), // None for ad-hoc requests
+                &config.method,
+                &config.url,
+                &req_headers,
+                &req_params,
+                req_body.as_deref(),
+                response.status,
+                &res_headers,
+                res_body_preview.as_deref(),
+                res_body_full.as_deref(),
+                res_body_type,
+                response.size_bytes as i64,
+                response.time_ms as i64,
+                res_timing.as_deref(),
+                response.content_type.as_deref(),
+                response.content_encoding.as_deref(),
+                assertion_results.as_deref(),
+                req_config.as_deref(),
+                res_file,
+            ).await
         }
-        
-        // Replace in Body
-        if let RequestBody::Raw { content, .. } = &mut config.body {
-            *content = content.replace(&pattern, value);
-        } else if let RequestBody::Json { value: json_val } = &mut config.body {
-            let json_str = json_val.to_string().replace(&pattern, value);
-            if let Ok(new_json) = serde_json::from_str(&json_str) {
-                *json_val = new_json;
-            }
+        SendResult::Failed { error, .. } => {
+            // Error messages may quote the URL or request values
+            let error_message = error.message().map(|m| redactor.redact_text(m));
+            db::add_failed_history_entry(
+                &history_id,
+                &workspaceId,
+                savedRequestId.as_deref(),
+                &config.method,
+                &config.url,
+                &req_headers,
+                &req_params,
+                req_body.as_deref(),
+                req_config.as_deref(),
+                error.kind(),
+                error_message.as_deref(),
+            ).await
         }
+    };
+    match saved {
+        Ok(()) => prune_history(Some(&workspaceId)).await,
+        Err(e) => eprintln!("Failed to save history: {:?}", e),
+    }
+
+    (result, redactor)
+}
+
+/// Run the introspection query against the request's endpoint and cache the
+/// schema for the saved request (when `requestId` is given)
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn introspect_graphql(
+    app: AppHandle,
+    mut config: RequestConfig,
+    workspaceId: String,
+    requestId: Option<String>,
+) -> Result<GraphQLSchema, String> {
+    let location = variable_location(requestId.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let variables = db::get_active_variables(&workspaceId, &location)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Keep URL, headers and auth, swap the body for the introspection query
+    config.method = "POST".to_string();
+    config.body = RequestBody::GraphQL {
+        query: graphql::INTROSPECTION_QUERY.to_string(),
+        variables: None,
+        operation_name: Some("IntrospectionQuery".to_string()),
+    };
+    // Schemas easily exceed the in-memory preview limit, so read them from disk
+    config.stream_to_file = true;
+    template::substitute_request(&mut config, &variables)?;
+
+    let cookies = db::get_cookies(&workspaceId)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut jar = CookieJar::new(&workspaceId, cookies);
+
+    let ctx = http::SendContext {
+        jar: &mut jar,
+        download_dir: response_files_dir(&app)?,
+        on_progress: None,
+    };
+    let result = http::execute_request(config.clone(), None, ctx).await;
+
+    if let Err(e) = db::save_cookie_jar(&jar).await {
+        eprintln!("Failed to save cookies: {:?}", e);
     }
+
+    let response = match result {
+        SendResult::Success { response, .. } => response,
+        SendResult::Failed { error, .. } => return Err(format!("Introspection request failed: {:?}", error)),
+    };
+    let ResponseBody::File { path, .. } = &response.body else {
+        return Err("Introspection response has no body".to_string());
+    };
+    let body = tokio::fs::read(path).await;
+    let _ = tokio::fs::remove_file(path).await;
+    let body = body.map_err(|e| e.to_string())?;
+
+    let schema = graphql::parse_introspection(&body).map_err(|e| {
+        format!("{} (HTTP {} {})", e, response.status, response.status_text)
+    })?;
+
+    let schema = GraphQLSchema {
+        request_id: requestId.unwrap_or_default(),
+        url: config.url,
+        schema,
+        fetched_at: chrono::Utc::now().timestamp_millis(),
+    };
+
+    if !schema.request_id.is_empty() {
+        db::save_graphql_schema(&schema)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(schema)
+}
+
+/// Get the cached GraphQL schema for a saved request
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_graphql_schema(requestId: String) -> Result<Option<GraphQLSchema>, String> {
+    db::get_graphql_schema(&requestId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Directory where streamed response bodies are written
+fn response_files_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("responses"))
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))
+}
+
+/// Resolve `path` and make sure it points at a streamed response file
+fn resolve_response_file(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let dir = response_files_dir(app)?
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    let file = PathBuf::from(path)
+        .canonicalize()
+        .map_err(|e| format!("Response file not found: {}", e))?;
+
+    if !file.starts_with(&dir) {
+        return Err("Not a response file".to_string());
+    }
+
+    Ok(file)
+}
+
+/// Copy a streamed response body to a user-chosen destination
+#[tauri::command]
+pub async fn save_response_file(app: AppHandle, path: String, destination: String) -> Result<(), String> {
+    let file = resolve_response_file(&app, &path)?;
+    tokio::fs::copy(&file, &destination)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Open a streamed response body with the system default application
+#[allow(deprecated)]
+#[tauri::command]
+pub async fn open_response_file(app: AppHandle, path: String) -> Result<(), String> {
+    let file = resolve_response_file(&app, &path)?;
+    app.shell()
+        .open(file.display().to_string(), None)
+        .map_err(|e| e.to_string())
 }
 
 /// Cancel an active HTTP request
 #[allow(non_snake_case)]
 #[tauri::command]
-pub fn cancel_request(requestId: String) -> bool {
-    http::cancel_request(&requestId)
+pub fn cancel_request(sendId: String) -> bool {
+    http::cancel_request(&sendId)
+}
+
+/// Run every request of a collection, or of one folder, in order. Emits a
+/// `runner-progress` event per request and returns the summary report.
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn run_collection(
+    app: AppHandle,
+    workspaceId: String,
+    collectionId: String,
+    folderId: Option<String>,
+    options: RunnerOptions,
+    runId: Option<String>,
+) -> Result<RunReport, String> {
+    let run_id = runId.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    runner::run_collection(&app, &workspaceId, &collectionId, folderId.as_deref(), &options, &run_id).await
+}
+
+/// Cancel an active collection run
+#[allow(non_snake_case)]
+#[tauri::command]
+pub fn cancel_run(runId: String) -> bool {
+    runner::cancel_run(&runId)
 }
 
 /// Get request history
 #[allow(non_snake_case)]
 #[tauri::command]
-pub async fn get_history(workspaceId: String, limit: i64, offset: i64) -> Result<serde_json::Value, String> {
-    let history = db::get_history(&workspaceId, limit, offset)
+pub async fn get_history(
+    workspaceId: String,
+    limit: i64,
+    offset: i64,
+    outcome: Option<HistoryOutcome>,
+) -> Result<serde_json::Value, String> {
+    let history = db::get_history(&workspaceId, limit, offset, outcome)
         .await
         .map_err(|e| e.to_string())?;
     
@@ -141,6 +508,87 @@ pub async fn clear_history(workspaceId: String) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+/// Search history by method, status, URL, time window, response time,
+/// linked request and body text
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn query_history(workspaceId: String, filter: HistoryFilter) -> Result<Vec<HistoryItem>, String> {
+    let url_regex = match filter.url_pattern.as_deref().filter(|p| !p.is_empty()) {
+        Some(pattern) => Some(regex::Regex::new(pattern).map_err(|e| format!("Invalid URL pattern: {}", e))?),
+        None => None,
+    };
+
+    db::query_history(&workspaceId, &filter, url_regex.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// History of a saved request with status and latency trend data, to see
+/// when an endpoint started misbehaving
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_request_timeline(
+    requestId: String,
+    since: Option<i64>,
+    limit: Option<i64>,
+) -> Result<RequestTimeline, String> {
+    let points = db::get_request_timeline(&requestId, since, limit.unwrap_or(500))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(trend::request_timeline(&requestId, points))
+}
+
+/// Get a history entry with its stored request and response bodies
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_history_entry(historyId: String) -> Result<HistoryEntry, String> {
+    db::get_history_entry(&historyId)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("History entry {} not found", historyId))
+}
+
+/// Send a history entry's request again, optionally diffing the response
+/// against the stored one. Credentials aren't stored, pass `auth` to resend them.
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn replay_history_entry(
+    app: AppHandle,
+    historyId: String,
+    sendId: Option<String>,
+    auth: Option<AuthConfig>,
+    compare: bool,
+) -> Result<ReplayResult, String> {
+    let entry = get_history_entry(historyId).await?;
+    let config = replay::request_config(&entry, auth)?;
+
+    let (result, redactor) = send_and_record(
+        app,
+        config,
+        entry.item.workspace_id.clone(),
+        sendId,
+        entry.item.request_id.clone(),
+        Vec::new(),
+    )
+    .await;
+
+    let diff = match &result {
+        SendResult::Success { response, .. } if compare => replay::diff_responses(&entry, response, &redactor),
+        _ => None,
+    };
+    Ok(ReplayResult { result, diff })
+}
+
+/// Report how much space history takes, for one workspace or all of them
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_history_usage(workspaceId: Option<String>) -> Result<HistoryUsage, String> {
+    db::get_history_usage(workspaceId.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Delete a single history entry
 #[allow(non_snake_case)]
 #[tauri::command]
@@ -150,7 +598,121 @@ pub async fn delete_history_entry(historyId: String) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
-/// Get all settings
+/// Fetch an OAuth 2.0 access token for the request's auth settings, using the
+/// cache unless `force` is set
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_oauth2_token(
+    app: AppHandle,
+    mut config: RequestConfig,
+    workspaceId: String,
+    savedRequestId: Option<String>,
+    force: bool,
+) -> Result<OAuth2Token, String> {
+    let location = variable_location(savedRequestId.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let variables = db::get_active_variables(&workspaceId, &location)
+        .await
+        .map_err(|e| e.to_string())?;
+    template::substitute_request(&mut config, &variables)?;
+
+    let Some(AuthConfig::OAuth2(oauth)) = config.auth.clone() else {
+        return Err("Request does not use OAuth 2.0".to_string());
+    };
+
+    let cookies = db::get_cookies(&workspaceId)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut jar = CookieJar::new(&workspaceId, cookies);
+    let download_dir = response_files_dir(&app)?;
+
+    let mut token_ctx = oauth::TokenContext {
+        app: &app,
+        workspace_id: &workspaceId,
+        template: &config,
+        jar: &mut jar,
+        download_dir: &download_dir,
+    };
+    let result = oauth::access_token(&oauth, &mut token_ctx, force).await;
+
+    if let Err(e) = db::save_cookie_jar(&jar).await {
+        eprintln!("Failed to save cookies: {:?}", e);
+    }
+
+    result.map_err(|e| format!("Failed to get access token: {:?}", e))
+}
+
+/// Forget the cached token for the request's OAuth 2.0 settings
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn clear_oauth2_token(config: OAuth2Config, workspaceId: String) -> Result<(), String> {
+    db::delete_oauth_token(&workspaceId, &oauth::cache_key(&config))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Forget all cached OAuth 2.0 tokens in a workspace
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn clear_oauth2_tokens(workspaceId: String) -> Result<(), String> {
+    db::clear_oauth_tokens(&workspaceId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Get all stored cookies for a workspace
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_cookies(workspaceId: String) -> Result<Vec<Cookie>, String> {
+    db::get_cookies(&workspaceId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Create or edit a cookie
+#[tauri::command]
+pub async fn save_cookie(mut cookie: Cookie) -> Result<String, String> {
+    if cookie.id.is_empty() {
+        cookie.id = uuid::Uuid::new_v4().to_string();
+    }
+    if cookie.created_at == 0 {
+        cookie.created_at = chrono::Utc::now().timestamp_millis();
+    }
+    cookie.domain = cookie.domain.trim_start_matches('.').to_ascii_lowercase();
+    if !cookie.path.starts_with('/') {
+        cookie.path = "/".to_string();
+    }
+
+    db::upsert_cookie(&cookie)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(cookie.id)
+}
+
+/// Delete a single cookie
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn delete_cookie(cookieId: String) -> Result<(), String> {
+    db::delete_cookie(&cookieId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Delete all cookies in a workspace
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn clear_cookies(workspaceId: String) -> Result<(), String> {
+    db::clear_cookies(&workspaceId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Setting stored encrypted with the secrets key
+const PROXY_PASSWORD_SETTING: &str = "proxy_password";
+
+/// Get all settings; a set proxy password is masked
 #[tauri::command]
 pub async fn get_settings() -> Result<HashMap<String, String>, String> {
     let settings = db::get_all_settings()
@@ -159,7 +721,14 @@ pub async fn get_settings() -> Result<HashMap<String, String>, String> {
 
     let map: HashMap<String, String> = settings
         .into_iter()
-        .map(|s| (s.key, s.value))
+        .map(|s| {
+            let value = if s.key == PROXY_PASSWORD_SETTING && !s.value.is_empty() {
+                secrets::SECRET_MASK.to_string()
+            } else {
+                s.value
+            };
+            (s.key, value)
+        })
         .collect();
 
     Ok(map)
@@ -168,19 +737,193 @@ pub async fn get_settings() -> Result<HashMap<String, String>, String> {
 /// Update a setting
 #[tauri::command]
 pub async fn update_setting(key: String, value: String) -> Result<(), String> {
-    // Update in database
-    db::update_setting(&key, &value)
+    // Key settings change only together with re-encrypting the secrets
+    if key.starts_with("secrets_") {
+        return Err("Use set_secrets_passphrase to change how secrets are encrypted".to_string());
+    }
+    // The UI only ever sees the mask for the proxy password, so keep what is stored
+    if key == PROXY_PASSWORD_SETTING && value == secrets::SECRET_MASK {
+        return Ok(());
+    }
+
+    let stored = if key == PROXY_PASSWORD_SETTING && !value.is_empty() {
+        secrets::encrypt(&value).map_err(|e| e.to_string())?
+    } else {
+        value.clone()
+    };
+
+    // Transport settings are applied to a new executor before they are
+    // stored, so an invalid proxy is rejected instead of persisted
+    let transport = key == "max_redirects" || key.starts_with("proxy_");
+    if transport {
+        let mut settings = transport_settings().await?;
+        settings.insert(key.clone(), value);
+        apply_transport_settings(&settings).await?;
+    }
+
+    // Update in database, going back to the stored transport settings on failure
+    if let Err(e) = db::update_setting(&key, &stored).await {
+        if transport {
+            reload_executor().await?;
+        }
+        return Err(e.to_string());
+    }
+
+    // Apply tightened retention limits right away
+    if matches!(key.as_str(), "history_limit" | "history_max_age_days" | "history_max_bytes") {
+        prune_history(None).await;
+    }
+
+    Ok(())
+}
+
+/// Rebuild the HTTP executor from the persisted transport settings
+pub async fn reload_executor() -> Result<(), String> {
+    apply_transport_settings(&transport_settings().await?).await
+}
+
+/// Persisted settings with the proxy password decrypted. While secrets are
+/// locked the proxy is used without a password.
+async fn transport_settings() -> Result<HashMap<String, String>, String> {
+    let mut settings: HashMap<String, String> = db::get_all_settings()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|s| (s.key, s.value))
+        .collect();
+
+    if let Some(stored) = settings.remove(PROXY_PASSWORD_SETTING) {
+        match secrets::decrypt(&stored) {
+            Ok(password) => {
+                settings.insert(PROXY_PASSWORD_SETTING.to_string(), password);
+            }
+            Err(e) => eprintln!("Proxy password unavailable: {}", e),
+        }
+    }
+
+    Ok(settings)
+}
+
+/// Rebuild the HTTP executor from transport settings and the stored host
+/// certificates
+async fn apply_transport_settings(settings: &HashMap<String, String>) -> Result<(), String> {
+    let max_redirects = settings
+        .get("max_redirects")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(10);
+
+    let mut certificates = db::get_host_certificates()
         .await
         .map_err(|e| e.to_string())?;
+    for cert in &mut certificates {
+        if let Some(stored) = cert.passphrase.take() {
+            match secrets::decrypt(&stored) {
+                Ok(passphrase) => cert.passphrase = Some(passphrase),
+                Err(e) => eprintln!("Passphrase of certificate for {} unavailable: {}", cert.host_pattern, e),
+            }
+        }
+    }
+
+    http::rebuild_executor(max_redirects, proxy_from_settings(settings), &certificates)
+        .await
+        .map_err(|e| format!("Failed to apply transport settings: {:?}", e))
+}
+
+/// Retention limits from the `history_*` settings; unparsable values disable a limit
+async fn history_retention() -> Result<HistoryRetention, sqlx::Error> {
+    let settings: HashMap<String, String> = db::get_all_settings()
+        .await?
+        .into_iter()
+        .map(|s| (s.key, s.value))
+        .collect();
+    let limit = |key: &str| settings.get(key).and_then(|v| v.trim().parse::<u64>().ok()).unwrap_or(0);
+
+    Ok(HistoryRetention {
+        max_entries: limit("history_limit"),
+        max_age_days: limit("history_max_age_days"),
+        max_bytes: limit("history_max_bytes"),
+    })
+}
+
+/// Enforce history retention for one workspace, or all of them with None
+pub async fn prune_history(workspace_id: Option<&str>) {
+    let result = match history_retention().await {
+        Ok(retention) => db::prune_history(workspace_id, &retention).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to prune history: {:?}", e);
+    }
+}
+
+/// Build the global proxy from `proxy_*` settings, None when disabled
+fn proxy_from_settings(settings: &HashMap<String, String>) -> Option<ProxyConfig> {
+    let get = |key: &str| settings.get(key).map(|v| v.trim()).unwrap_or("");
 
-    // If max_redirects changed, rebuild HTTP executor
-    if key == "max_redirects" {
-        if let Ok(max_redirects) = value.parse::<usize>() {
-            http::rebuild_executor(max_redirects).await;
+    if get("proxy_enabled") != "true" || get("proxy_url").is_empty() {
+        return None;
+    }
+
+    Some(ProxyConfig {
+        enabled: true,
+        url: get("proxy_url").to_string(),
+        username: Some(get("proxy_username").to_string()).filter(|u| !u.is_empty()),
+        password: Some(get(PROXY_PASSWORD_SETTING).to_string()).filter(|p| !p.is_empty()),
+        bypass: get("proxy_bypass")
+            .split(',')
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+            .collect(),
+    })
+}
+
+/// Get all host certificate entries; set passphrases are masked
+#[tauri::command]
+pub async fn get_host_certificates() -> Result<Vec<HostCertificate>, String> {
+    let mut certificates = db::get_host_certificates()
+        .await
+        .map_err(|e| e.to_string())?;
+    for cert in &mut certificates {
+        if cert.passphrase.as_deref().is_some_and(|p| !p.is_empty()) {
+            cert.passphrase = Some(secrets::SECRET_MASK.to_string());
         }
     }
+    Ok(certificates)
+}
 
-    Ok(())
+/// Create or edit a host certificate entry and apply it to the executor
+#[tauri::command]
+pub async fn save_host_certificate(mut cert: HostCertificate) -> Result<String, String> {
+    if cert.id.is_empty() {
+        cert.id = uuid::Uuid::new_v4().to_string();
+    }
+    if cert.created_at == 0 {
+        cert.created_at = chrono::Utc::now().timestamp_millis();
+    }
+
+    db::upsert_host_certificate(&cert)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    reload_executor().await?;
+
+    // The entry is kept either way; a broken one only fails requests to its hosts
+    if let Some(e) = http::certificate_error(&cert.id).await {
+        return Err(format!("Certificate saved but can't be used: {:?}", e));
+    }
+
+    Ok(cert.id)
+}
+
+/// Delete a host certificate entry
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn delete_host_certificate(certificateId: String) -> Result<(), String> {
+    db::delete_host_certificate(&certificateId)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    reload_executor().await
 }
 
 /// Create a new collection
@@ -235,6 +978,8 @@ pub async fn save_request(
     params: String,
     body: String,
     auth: Option<String>,
+    scripts: Option<String>,
+    assertions: Option<String>,
 ) -> Result<String, String> {
     let id = uuid::Uuid::new_v4().to_string();
     
@@ -255,6 +1000,8 @@ pub async fn save_request(
         &params,
         &body,
         auth.as_deref(),
+        scripts.as_deref(),
+        assertions.as_deref(),
         sort_order,
     )
     .await
@@ -289,8 +1036,8 @@ pub async fn get_collections_with_folders(workspaceId: String) -> Result<serde_j
             // Filter requests for this folder
             let folder_requests: Vec<serde_json::Value> = all_requests
                 .iter()
-                .filter(|(_, _, _, _, r_folder_id, _, _, _, _)| r_folder_id == &f_id)
-                .map(|(r_id, r_name, r_method, r_url, _, r_headers, r_params, r_body, r_auth)| {
+                .filter(|(_, _, _, _, r_folder_id, _, _, _, _, _, _)| r_folder_id == &f_id)
+                .map(|(r_id, r_name, r_method, r_url, _, r_headers, r_params, r_body, r_auth, r_scripts, r_assertions)| {
                     serde_json::json!({
                         "id": r_id,
                         "name": r_name,
@@ -303,7 +1050,9 @@ pub async fn get_collections_with_folders(workspaceId: String) -> Result<serde_j
                             serde_json::json!({"type": "none"})
                         } else {
                             serde_json::from_str::<serde_json::Value>(r_auth).unwrap_or(serde_json::json!({"type": "none"}))
-                        }
+                        },
+                        "scripts": serde_json::from_str::<RequestScripts>(r_scripts).unwrap_or_default(),
+                        "assertions": serde_json::from_str::<Vec<Assertion>>(r_assertions).unwrap_or_default()
                     })
                 })
                 .collect();
@@ -318,8 +1067,8 @@ pub async fn get_collections_with_folders(workspaceId: String) -> Result<serde_j
         // Root requests (folder_id is empty or doesn't match any folder)
         let root_requests: Vec<serde_json::Value> = all_requests
             .into_iter()
-            .filter(|(_, _, _, _, r_folder_id, _, _, _, _)| r_folder_id.is_empty())
-            .map(|(r_id, r_name, r_method, r_url, _, r_headers, r_params, r_body, r_auth)| {
+            .filter(|(_, _, _, _, r_folder_id, _, _, _, _, _, _)| r_folder_id.is_empty())
+            .map(|(r_id, r_name, r_method, r_url, _, r_headers, r_params, r_body, r_auth, r_scripts, r_assertions)| {
                 serde_json::json!({
                     "id": r_id,
                     "name": r_name,
@@ -332,7 +1081,9 @@ pub async fn get_collections_with_folders(workspaceId: String) -> Result<serde_j
                         serde_json::json!({"type": "none"})
                     } else {
                         serde_json::from_str::<serde_json::Value>(&r_auth).unwrap_or(serde_json::json!({"type": "none"}))
-                    }
+                    },
+                    "scripts": serde_json::from_str::<RequestScripts>(&r_scripts).unwrap_or_default(),
+                    "assertions": serde_json::from_str::<Vec<Assertion>>(&r_assertions).unwrap_or_default()
                 })
             })
             .collect();
@@ -413,7 +1164,7 @@ pub async fn get_variables(environmentId: String) -> Result<serde_json::Value, S
             serde_json::json!({
                 "id": id,
                 "key": key,
-                "value": value,
+                "value": if is_secret { secrets::SECRET_MASK.to_string() } else { value },
                 "is_secret": is_secret
             })
         })
@@ -422,22 +1173,141 @@ pub async fn get_variables(environmentId: String) -> Result<serde_json::Value, S
     Ok(serde_json::json!(list))
 }
 
-/// Get active variables for workspace
+/// Persist variables set by a script and apply them to the in-memory set.
+/// Returns the values of secret variables so they can be redacted.
+async fn apply_script_variables(
+    workspace_id: &str,
+    values: &[(String, String)],
+    variables: &mut Vec<(String, String)>,
+) -> Vec<String> {
+    let mut secrets = Vec::new();
+    for (key, value) in values {
+        match db::set_active_variable(workspace_id, key, value).await {
+            Ok(true) => secrets.push(value.clone()),
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to save script variable {}: {}", key, e),
+        }
+        set_variable(variables, key.clone(), value.clone());
+    }
+    secrets
+}
+
+/// Set or add a variable in a resolved variable list
+fn set_variable(variables: &mut Vec<(String, String)>, key: String, value: String) {
+    match variables.iter_mut().find(|(k, _)| *k == key) {
+        Some(existing) => existing.1 = value,
+        None => variables.push((key, value)),
+    }
+}
+
+/// Collection and folders of a saved request; unsaved requests only see
+/// environment and global variables
+async fn variable_location(saved_request_id: Option<&str>) -> Result<VariableLocation, sqlx::Error> {
+    match saved_request_id {
+        Some(id) => db::get_request_location(id).await,
+        None => Ok(VariableLocation::default()),
+    }
+}
+
+/// Get active variables for workspace, with secret values masked
 #[allow(non_snake_case)]
 #[tauri::command]
-pub async fn get_active_variables(workspaceId: String) -> Result<serde_json::Value, String> {
-    let variables = db::get_active_variables(&workspaceId)
-        .await
-        .map_err(|e| e.to_string())?;
-    
+pub async fn get_active_variables(
+    workspaceId: String,
+    savedRequestId: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let variables = get_resolved_variables(workspaceId, savedRequestId).await?;
+
     let mut map = serde_json::Map::new();
-    for (key, value) in variables {
-        map.insert(key, serde_json::Value::String(value));
+    for variable in variables {
+        map.insert(variable.key, serde_json::Value::String(variable.value));
     }
     
     Ok(serde_json::Value::Object(map))
 }
 
+/// Get the variables visible to a request with the scope each value comes
+/// from, secret values masked
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_resolved_variables(
+    workspaceId: String,
+    savedRequestId: Option<String>,
+) -> Result<Vec<ResolvedVariable>, String> {
+    let location = variable_location(savedRequestId.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let variables = db::list_active_variables(&workspaceId, &location)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(variables
+        .into_iter()
+        .map(|mut variable| {
+            if variable.is_secret {
+                variable.value = secrets::SECRET_MASK.to_string();
+            }
+            variable
+        })
+        .collect())
+}
+
+/// Get variables defined on a workspace (global), collection, folder or environment
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn get_scope_variables(scope: VariableScope, ownerId: String) -> Result<serde_json::Value, String> {
+    let variables = db::get_scope_variables(scope, &ownerId)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let list: Vec<serde_json::Value> = variables
+        .into_iter()
+        .map(|(id, key, value, is_secret)| {
+            serde_json::json!({
+                "id": id,
+                "key": key,
+                "value": if is_secret { secrets::SECRET_MASK.to_string() } else { value },
+                "is_secret": is_secret
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!(list))
+}
+
+/// Upsert multiple variables of a workspace (global), collection, folder or environment
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn save_scope_variables(
+    scope: VariableScope,
+    ownerId: String,
+    vars: Vec<serde_json::Value>,
+) -> Result<(), String> {
+    if scope == VariableScope::Local {
+        return Err("Request-local variables are not stored".to_string());
+    }
+
+    for v in vars {
+        let id = v["id"].as_str().unwrap_or(&uuid::Uuid::new_v4().to_string()).to_string();
+        let key = v["key"].as_str().unwrap_or("");
+        let value = v["value"].as_str().unwrap_or("");
+        let is_secret = v["is_secret"].as_bool().unwrap_or(false);
+
+        db::upsert_scope_variable(scope, &id, &ownerId, key, value, is_secret)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[tauri::command]
+pub async fn delete_scope_variable(scope: VariableScope, variableId: String) -> Result<(), String> {
+    db::delete_scope_variable(scope, &variableId)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 
 /// Upsert multiple variables
 #[allow(non_snake_case)]
@@ -494,6 +1364,8 @@ pub async fn update_request(
     params: String,
     body: String,
     auth: Option<String>,
+    scripts: Option<String>,
+    assertions: Option<String>,
 ) -> Result<(), String> {
     db::update_request(
         &id,
@@ -504,6 +1376,8 @@ pub async fn update_request(
         &params,
         &body,
         auth.as_deref(),
+        scripts.as_deref(),
+        assertions.as_deref(),
     )
     .await
     .map_err(|e| e.to_string())
@@ -566,7 +1440,33 @@ pub async fn import_collection(collection: ImportCollection) -> Result<String, S
 /// Clear all app data (workspaces, collections, history, environments, settings) and reset to defaults.
 #[tauri::command]
 pub async fn clear_all_data() -> Result<(), String> {
-    db::clear_all_data().await.map_err(|e| e.to_string())
+    db::clear_all_data().await.map_err(|e| e.to_string())?;
+
+    // Settings were reset, so secrets go back to the local key file
+    secrets::reload().await.map_err(|e| e.to_string())
+}
+
+/// Get how secret variables are encrypted and whether they are unlocked
+#[tauri::command]
+pub async fn get_secrets_status() -> Result<SecretStatus, String> {
+    secrets::status().await.map_err(|e| e.to_string())
+}
+
+/// Unlock secret variables with the user's passphrase
+#[tauri::command]
+pub async fn unlock_secrets(passphrase: String) -> Result<(), String> {
+    secrets::unlock(&passphrase).await.map_err(|e| e.to_string())?;
+
+    // The proxy password can be decrypted now
+    reload_executor().await
+}
+
+/// Protect secret variables with a passphrase, or use the local key file again when None
+#[tauri::command]
+pub async fn set_secrets_passphrase(passphrase: Option<String>) -> Result<(), String> {
+    secrets::set_passphrase(passphrase.as_deref())
+        .await
+        .map_err(|e| e.to_string())
 }
 
 
@@ -0,0 +1,523 @@
+use crate::models::Cookie;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    /// Jar of the send currently running on this task
+    static SEND_JAR: Arc<Mutex<CookieJar>>;
+}
+
+lazy_static! {
+    /// Session cookies (no Expires or Max-Age) by workspace. They are never
+    /// written to the database and are gone when the app exits.
+    static ref SESSION_COOKIES: DashMap<String, Vec<Cookie>> = DashMap::new();
+}
+
+/// In-memory view of a workspace's cookies for the duration of a single send.
+///
+/// The jar is loaded from the `cookie` table and the session cookies before
+/// the request, consulted for the `Cookie` header of every hop, and fed every
+/// `Set-Cookie` received along the way, redirects included.
+/// Cookies that were created, updated or expired are tracked separately so the
+/// caller only has to persist what actually changed.
+pub struct CookieJar {
+    workspace_id: String,
+    cookies: Vec<Cookie>,
+    changed: Vec<Cookie>,
+    removed: Vec<String>,
+}
+
+impl CookieJar {
+    /// Create a jar from previously persisted cookies
+    pub fn new(workspace_id: &str, cookies: Vec<Cookie>) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            cookies,
+            changed: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Cookies created or updated since the jar was loaded
+    pub fn changed(&self) -> &[Cookie] {
+        &self.changed
+    }
+
+    /// Ids of persisted cookies that were expired or overwritten by the server
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Build the `Cookie` header value for a request to `url`
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let now = Utc::now().timestamp_millis();
+        let is_secure = url.scheme() == "https";
+
+        let mut matching: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|c| c.expires_at.map(|exp| exp > now).unwrap_or(true))
+            .filter(|c| !c.secure || is_secure)
+            .filter(|c| {
+                if c.host_only {
+                    c.domain == host
+                } else {
+                    domain_matches(&host, &c.domain)
+                }
+            })
+            .filter(|c| path_matches(url.path(), &c.path))
+            .collect();
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        // RFC 6265 5.4: longer paths first, then earlier creation time
+        matching.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created_at.cmp(&b.created_at))
+        });
+
+        Some(
+            matching
+                .iter()
+                .map(|c| format!("{}={}", c.name, c.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// Store all `Set-Cookie` headers received from `url`
+    pub fn store_response_cookies<'a, I>(&mut self, url: &Url, headers: I)
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        for header in headers {
+            if let Ok(raw) = header.to_str() {
+                if let Some(cookie) = parse_set_cookie(raw, url, &self.workspace_id) {
+                    self.store(cookie);
+                }
+            }
+        }
+    }
+
+    /// Run `future` with this jar as the cookie store of every request it
+    /// sends through a client using [`SendCookieStore`]
+    pub async fn scope<F: Future>(&mut self, future: F) -> F::Output {
+        let placeholder = CookieJar::new(&self.workspace_id, Vec::new());
+        let shared = Arc::new(Mutex::new(std::mem::replace(self, placeholder)));
+        let output = SEND_JAR.scope(shared.clone(), future).await;
+
+        let mut jar = shared.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::swap(self, &mut *jar);
+        output
+    }
+
+    /// Insert or replace a cookie, keyed by (name, domain, path)
+    fn store(&mut self, mut cookie: Cookie) {
+        let existing = self.cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        });
+
+        if let Some(index) = existing {
+            let old = self.cookies.remove(index);
+            // Keep the original creation time so ordering stays stable
+            cookie.id = old.id.clone();
+            cookie.created_at = old.created_at;
+            self.changed.retain(|c| c.id != old.id);
+            if !self.removed.contains(&old.id) {
+                self.removed.push(old.id);
+            }
+        }
+
+        let now = Utc::now().timestamp_millis();
+        if cookie.expires_at.map(|exp| exp <= now).unwrap_or(false) {
+            // Server asked to delete the cookie
+            return;
+        }
+
+        self.removed.retain(|id| id != &cookie.id);
+        self.changed.push(cookie.clone());
+        self.cookies.push(cookie);
+    }
+}
+
+/// Cookie store registered on every pooled client. Clients are shared across
+/// workspaces, so cookies are read from and stored into the jar of the send
+/// running on the current task; requests sent outside [`CookieJar::scope`]
+/// carry no cookies.
+pub struct SendCookieStore;
+
+impl CookieStore for SendCookieStore {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let _ = SEND_JAR.try_with(|jar| {
+            jar.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .store_response_cookies(url, cookie_headers);
+        });
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        SEND_JAR
+            .try_with(|jar| jar.lock().unwrap_or_else(|e| e.into_inner()).header_for(url))
+            .ok()
+            .flatten()
+            .and_then(|header| HeaderValue::from_str(&header).ok())
+    }
+}
+
+/// Session cookies kept in memory for a workspace
+pub fn session_cookies(workspace_id: &str) -> Vec<Cookie> {
+    SESSION_COOKIES
+        .get(workspace_id)
+        .map(|cookies| cookies.clone())
+        .unwrap_or_default()
+}
+
+/// Keep a session cookie in memory, replacing one with the same id or
+/// (name, domain, path)
+pub fn keep_session_cookie(cookie: &Cookie) {
+    let mut cookies = SESSION_COOKIES.entry(cookie.workspace_id.clone()).or_default();
+    cookies.retain(|c| {
+        c.id != cookie.id && !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path)
+    });
+    cookies.push(cookie.clone());
+}
+
+/// Drop a session cookie by id
+pub fn forget_session_cookie(id: &str) {
+    for mut cookies in SESSION_COOKIES.iter_mut() {
+        cookies.retain(|c| c.id != id);
+    }
+}
+
+/// Drop all session cookies of a workspace, or of every workspace
+pub fn clear_session_cookies(workspace_id: Option<&str>) {
+    match workspace_id {
+        Some(workspace_id) => {
+            SESSION_COOKIES.remove(workspace_id);
+        }
+        None => SESSION_COOKIES.clear(),
+    }
+}
+
+/// Parse a single `Set-Cookie` header value received from `url`
+fn parse_set_cookie(raw: &str, url: &Url, workspace_id: &str) -> Option<Cookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = raw.split(';');
+
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let now = Utc::now().timestamp_millis();
+    let mut domain: Option<String> = None;
+    let mut path: Option<String> = None;
+    let mut expires_at: Option<i64> = None;
+    let mut max_age: Option<i64> = None;
+    let mut secure = false;
+    let mut http_only = false;
+    let mut same_site: Option<String> = None;
+
+    for attr in parts {
+        let (attr_name, attr_value) = match attr.split_once('=') {
+            Some((n, v)) => (n.trim(), v.trim()),
+            None => (attr.trim(), ""),
+        };
+
+        match attr_name.to_ascii_lowercase().as_str() {
+            "domain" if !attr_value.is_empty() => {
+                domain = Some(attr_value.trim_start_matches('.').to_ascii_lowercase());
+            }
+            "path" if attr_value.starts_with('/') => {
+                path = Some(attr_value.to_string());
+            }
+            "expires" => {
+                expires_at = parse_cookie_date(attr_value);
+            }
+            "max-age" => {
+                if let Ok(seconds) = attr_value.parse::<i64>() {
+                    max_age = Some(now.saturating_add(seconds.saturating_mul(1000)));
+                }
+            }
+            "secure" => secure = true,
+            "httponly" => http_only = true,
+            "samesite" if !attr_value.is_empty() => {
+                same_site = Some(attr_value.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    // Secure cookies may only be set over https
+    if secure && url.scheme() != "https" {
+        return None;
+    }
+
+    let (domain, host_only) = match domain {
+        Some(d) if d == host => (d, false),
+        Some(d) => {
+            // Reject cookies for domains the response host doesn't belong to
+            if !domain_matches(&host, &d) {
+                return None;
+            }
+            (d, false)
+        }
+        None => (host, true),
+    };
+
+    Some(Cookie {
+        id: uuid::Uuid::new_v4().to_string(),
+        workspace_id: workspace_id.to_string(),
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain,
+        path: path.unwrap_or_else(|| default_path(url.path())),
+        // Max-Age takes precedence over Expires
+        expires_at: max_age.or(expires_at),
+        secure,
+        http_only,
+        host_only,
+        same_site,
+        created_at: now,
+    })
+}
+
+/// Parse the date formats servers commonly send in `Expires`
+fn parse_cookie_date(value: &str) -> Option<i64> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.timestamp_millis());
+    }
+
+    let formats = [
+        "%a, %d-%b-%Y %H:%M:%S GMT",
+        "%a, %d-%b-%y %H:%M:%S GMT",
+        "%A, %d-%b-%y %H:%M:%S GMT",
+        "%a %b %e %H:%M:%S %Y",
+    ];
+
+    formats
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .map(|date| date.and_utc().timestamp_millis())
+}
+
+/// RFC 6265 5.1.3 domain matching
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// RFC 6265 5.1.4 path matching
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    if request_path == cookie_path {
+        return true;
+    }
+
+    request_path.starts_with(cookie_path)
+        && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/'))
+}
+
+/// RFC 6265 5.1.4 default-path of a request URI
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn parse(raw: &str, from: &str) -> Option<Cookie> {
+        parse_set_cookie(raw, &url(from), "ws")
+    }
+
+    #[test]
+    fn parses_name_value_and_attributes() {
+        let cookie = parse(
+            "sid=abc123; Domain=.Example.com; Path=/api; Secure; HttpOnly; SameSite=Lax",
+            "https://www.example.com/login",
+        )
+        .unwrap();
+
+        assert_eq!(cookie.name, "sid");
+        assert_eq!(cookie.value, "abc123");
+        assert_eq!(cookie.domain, "example.com");
+        assert_eq!(cookie.path, "/api");
+        assert!(cookie.secure && cookie.http_only && !cookie.host_only);
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+        assert_eq!(cookie.expires_at, None);
+        assert_eq!(cookie.workspace_id, "ws");
+    }
+
+    #[test]
+    fn defaults_to_host_only_and_request_directory() {
+        let cookie = parse("a=1", "http://example.com/app/login?next=/").unwrap();
+        assert_eq!(cookie.domain, "example.com");
+        assert!(cookie.host_only);
+        assert_eq!(cookie.path, "/app");
+
+        let cookie = parse("a=1; Path=relative", "http://example.com/login").unwrap();
+        assert_eq!(cookie.path, "/");
+    }
+
+    #[test]
+    fn max_age_wins_over_expires() {
+        let before = Utc::now().timestamp_millis();
+        let cookie = parse(
+            "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60",
+            "http://example.com/",
+        )
+        .unwrap();
+        let expires_at = cookie.expires_at.unwrap();
+        assert!(expires_at >= before + 60_000 && expires_at <= before + 61_000);
+
+        let cookie = parse("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", "http://example.com/").unwrap();
+        assert_eq!(cookie.expires_at, Some(1_445_412_480_000));
+    }
+
+    #[test]
+    fn parses_common_expires_formats() {
+        let expected = Some(1_445_412_480_000);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), expected);
+        assert_eq!(parse_cookie_date("Wed, 21-Oct-2015 07:28:00 GMT"), expected);
+        assert_eq!(parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"), expected);
+        assert_eq!(parse_cookie_date("Wed Oct 21 07:28:00 2015"), expected);
+        assert_eq!(parse_cookie_date("not a date"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_cookies() {
+        // No name
+        assert!(parse("=value", "http://example.com/").is_none());
+        assert!(parse("novalue", "http://example.com/").is_none());
+        // Secure over plain http
+        assert!(parse("a=1; Secure", "http://example.com/").is_none());
+        // Domain the host doesn't belong to
+        assert!(parse("a=1; Domain=other.com", "http://example.com/").is_none());
+        assert!(parse("a=1; Domain=ample.com", "http://example.com/").is_none());
+    }
+
+    #[test]
+    fn matches_domains() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("api.example.com", "example.com"));
+        assert!(domain_matches("a.b.example.com", "example.com"));
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com", "api.example.com"));
+        // IP addresses only match exactly
+        assert!(domain_matches("10.0.0.1", "10.0.0.1"));
+        assert!(!domain_matches("10.0.0.1", "0.0.1"));
+    }
+
+    #[test]
+    fn matches_paths() {
+        assert!(path_matches("/", "/"));
+        assert!(path_matches("/api", "/api"));
+        assert!(path_matches("/api/users", "/api"));
+        assert!(path_matches("/api/users", "/api/"));
+        assert!(!path_matches("/apiv2", "/api"));
+        assert!(!path_matches("/", "/api"));
+    }
+
+    #[test]
+    fn computes_default_path() {
+        assert_eq!(default_path(""), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path("/login"), "/");
+        assert_eq!(default_path("/app/login"), "/app");
+        assert_eq!(default_path("/app/v1/"), "/app/v1");
+    }
+
+    #[test]
+    fn jar_builds_header_for_matching_cookies() {
+        let mut jar = CookieJar::new("ws", Vec::new());
+        let headers = [
+            HeaderValue::from_static("root=1; Path=/"),
+            HeaderValue::from_static("api=2; Path=/api"),
+            HeaderValue::from_static("secure=3; Path=/; Secure"),
+            HeaderValue::from_static("wide=4; Path=/; Domain=example.com"),
+        ];
+        jar.store_response_cookies(&url("https://example.com/api/login"), &headers);
+
+        assert_eq!(
+            jar.header_for(&url("https://example.com/api/users")).as_deref(),
+            Some("api=2; root=1; secure=3; wide=4")
+        );
+        // Host-only cookies stay on their host, domain cookies follow subdomains
+        assert_eq!(jar.header_for(&url("http://www.example.com/api")).as_deref(), Some("wide=4"));
+        assert_eq!(jar.header_for(&url("https://other.com/")), None);
+    }
+
+    #[test]
+    fn jar_replaces_and_expires_cookies() {
+        let mut jar = CookieJar::new("ws", Vec::new());
+        let origin = url("http://example.com/");
+        jar.store_response_cookies(&origin, &[HeaderValue::from_static("a=1")]);
+        let id = jar.changed()[0].id.clone();
+
+        jar.store_response_cookies(&origin, &[HeaderValue::from_static("a=2")]);
+        assert_eq!(jar.changed().len(), 1);
+        assert_eq!(jar.changed()[0].id, id);
+        assert_eq!(jar.header_for(&origin).as_deref(), Some("a=2"));
+
+        jar.store_response_cookies(&origin, &[HeaderValue::from_static("a=; Max-Age=0")]);
+        assert!(jar.changed().is_empty());
+        assert_eq!(jar.removed(), &[id]);
+        assert_eq!(jar.header_for(&origin), None);
+    }
+
+    #[tokio::test]
+    async fn store_reads_and_writes_the_scoped_jar() {
+        let store = SendCookieStore;
+        let hop = url("http://auth.example.com/login");
+        let mut jar = CookieJar::new("ws", Vec::new());
+
+        jar.scope(async {
+            let headers = [HeaderValue::from_static("sid=1")];
+            store.set_cookies(&mut headers.iter(), &hop);
+            assert_eq!(store.cookies(&hop), Some(HeaderValue::from_static("sid=1")));
+        })
+        .await;
+
+        assert_eq!(jar.changed().len(), 1);
+        assert_eq!(jar.header_for(&hop).as_deref(), Some("sid=1"));
+        // Outside a send there is no jar to use
+        assert_eq!(store.cookies(&hop), None);
+    }
+
+    #[test]
+    fn session_cookies_are_replaced_by_key() {
+        let origin = url("http://example.com/");
+        let first = parse_set_cookie("sid=1", &origin, "session-test").unwrap();
+        let second = parse_set_cookie("sid=2", &origin, "session-test").unwrap();
+
+        keep_session_cookie(&first);
+        keep_session_cookie(&second);
+        let kept = session_cookies("session-test");
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].value, "2");
+
+        forget_session_cookie(&second.id);
+        assert!(session_cookies("session-test").is_empty());
+    }
+}
@@ -1,5 +1,13 @@
-use crate::models::{Setting, ImportCollection, ImportFolder};
-use sqlx::{sqlite::SqlitePool, Pool, Sqlite};
+use crate::cookies::{self, CookieJar};
+use crate::secrets;
+use crate::models::{
+    Cookie, GraphQLSchema, HistoryEntry, HistoryFilter, HistoryOutcome, HistoryRetention, HistoryUsage, HostCertificate,
+    OAuth2Token, ResolvedVariable, Setting, ImportCollection, ImportFolder, TimelinePoint, VariableLocation,
+    VariableScope,
+};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use regex::Regex;
+use sqlx::{Pool, QueryBuilder, Row, Sqlite};
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
 use tokio::sync::RwLock;
@@ -46,6 +54,65 @@ pub async fn get_pool() -> Result<Pool<Sqlite>, sqlx::Error> {
     DB_POOL.read().await.clone().ok_or(sqlx::Error::PoolClosed)
 }
 
+/// Schema of the history table. The response columns are null when the send
+/// failed, in which case error_kind/error_message describe the HttpError.
+fn history_table_sql(table: &str) -> String {
+    format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            id TEXT PRIMARY KEY,
+            workspace_id TEXT NOT NULL REFERENCES workspace(id),
+            request_id TEXT REFERENCES request(id),
+            method TEXT NOT NULL,
+            url TEXT NOT NULL,
+            request_headers TEXT NOT NULL,
+            request_params TEXT NOT NULL DEFAULT '[]',
+            request_body TEXT,
+            response_status INTEGER,
+            response_headers TEXT,
+            response_body_preview TEXT,
+            response_body_full TEXT,
+            response_body_type TEXT,
+            response_size_bytes INTEGER,
+            response_time_ms INTEGER,
+            response_timing TEXT,
+            content_type TEXT,
+            content_encoding TEXT,
+            assertion_results TEXT,
+            request_config TEXT,
+            response_file TEXT,
+            error_kind TEXT,
+            error_message TEXT,
+            created_at INTEGER NOT NULL
+        )
+        "#,
+        table
+    )
+}
+
+/// Columns of history tables created before failed sends were recorded
+const HISTORY_V1_COLUMNS: &str = "id, workspace_id, request_id, method, url, \
+    request_headers, request_params, request_body, response_status, response_headers, \
+    response_body_preview, response_body_full, response_body_type, response_size_bytes, \
+    response_time_ms, response_timing, content_type, content_encoding, assertion_results, created_at";
+
+/// Values `history_fts` indexes for a history row (`row` is `new.`, `old.`
+/// or empty). Binary previews are hex, so response bodies of binary rows are
+/// left out. The 'delete' command must be given exactly what was inserted,
+/// so the index is always filled through this list rather than 'rebuild'.
+fn history_fts_values(row: &str) -> String {
+    format!(
+        "{0}rowid, {0}url, {0}request_body, \
+         CASE WHEN {0}response_body_type = 'binary' THEN NULL ELSE {0}response_body_preview END, \
+         CASE WHEN {0}response_body_type = 'binary' THEN NULL ELSE {0}response_body_full END, \
+         {0}error_message",
+        row
+    )
+}
+
+/// Columns of `history_fts`, named after the `history` columns they index
+const HISTORY_FTS_COLUMNS: &str = "url, request_body, response_body_preview, response_body_full, error_message";
+
 /// Run database migrations
 async fn run_migrations(pool: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::Error>> {
     // Create schema_version table
@@ -170,44 +237,118 @@ async fn run_migrations(pool: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::E
     .execute(pool)
     .await?;
 
+    // Create scoped variable tables (workspace globals, collection and folder variables)
+    for (table, owner) in [
+        ("global_variable", "workspace_id TEXT NOT NULL REFERENCES workspace(id)"),
+        ("collection_variable", "collection_id TEXT NOT NULL REFERENCES collection(id)"),
+        ("folder_variable", "folder_id TEXT NOT NULL REFERENCES folder(id)"),
+    ] {
+        sqlx::query(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id TEXT PRIMARY KEY,
+                {},
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                is_secret INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+            table, owner
+        ))
+        .execute(pool)
+        .await?;
+    }
+
     // Create history table
+    sqlx::query(&history_table_sql("history"))
+        .execute(pool)
+        .await?;
+
+    // Create tab_state table
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS history (
+        CREATE TABLE IF NOT EXISTS tab_state (
             id TEXT PRIMARY KEY,
-            workspace_id TEXT NOT NULL REFERENCES workspace(id),
             request_id TEXT REFERENCES request(id),
-            method TEXT NOT NULL,
+            draft_data TEXT NOT NULL,
+            draft_version INTEGER NOT NULL DEFAULT 1,
+            tab_order INTEGER NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create cookie table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS cookie (
+            id TEXT PRIMARY KEY,
+            workspace_id TEXT NOT NULL REFERENCES workspace(id),
+            name TEXT NOT NULL,
+            value TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            path TEXT NOT NULL,
+            expires_at INTEGER,
+            secure INTEGER NOT NULL DEFAULT 0,
+            http_only INTEGER NOT NULL DEFAULT 0,
+            host_only INTEGER NOT NULL DEFAULT 1,
+            same_site TEXT,
+            created_at INTEGER NOT NULL,
+            UNIQUE (workspace_id, name, domain, path)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create oauth_token table (access tokens cached per workspace and OAuth 2.0 config)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS oauth_token (
+            workspace_id TEXT NOT NULL REFERENCES workspace(id),
+            cache_key TEXT NOT NULL,
+            access_token TEXT NOT NULL,
+            refresh_token TEXT,
+            token_type TEXT NOT NULL,
+            scope TEXT,
+            expires_at INTEGER,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (workspace_id, cache_key)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create graphql_schema table (introspection cache per saved request)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS graphql_schema (
+            request_id TEXT PRIMARY KEY,
             url TEXT NOT NULL,
-            request_headers TEXT NOT NULL,
-            request_params TEXT NOT NULL DEFAULT '[]',
-            request_body TEXT,
-            response_status INTEGER NOT NULL,
-            response_headers TEXT NOT NULL,
-            response_body_preview TEXT,
-            response_body_full TEXT,
-            response_body_type TEXT NOT NULL,
-            response_size_bytes INTEGER NOT NULL,
-            response_time_ms INTEGER NOT NULL,
-            content_type TEXT,
-            content_encoding TEXT,
-            created_at INTEGER NOT NULL
+            schema TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL
         )
         "#,
     )
     .execute(pool)
     .await?;
 
-    // Create tab_state table
+    // Create host_certificate table
     sqlx::query(
         r#"
-        CREATE TABLE IF NOT EXISTS tab_state (
+        CREATE TABLE IF NOT EXISTS host_certificate (
             id TEXT PRIMARY KEY,
-            request_id TEXT REFERENCES request(id),
-            draft_data TEXT NOT NULL,
-            draft_version INTEGER NOT NULL DEFAULT 1,
-            tab_order INTEGER NOT NULL,
-            is_active INTEGER NOT NULL DEFAULT 0
+            host_pattern TEXT NOT NULL,
+            cert_format TEXT NOT NULL DEFAULT 'pem',
+            cert_path TEXT,
+            key_path TEXT,
+            passphrase TEXT,
+            ca_path TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL
         )
         "#,
     )
@@ -227,6 +368,122 @@ async fn run_migrations(pool: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::E
     .execute(pool)
     .await; // Ignore error if column already exists
 
+    // Migration: Add response_timing column (JSON phase breakdown) to history
+    let _ = sqlx::query("ALTER TABLE history ADD COLUMN response_timing TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Add scripts column (JSON pre-request/post-response scripts) to request
+    let _ = sqlx::query("ALTER TABLE request ADD COLUMN scripts TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Add assertions column (JSON Vec<Assertion>) to request
+    let _ = sqlx::query("ALTER TABLE request ADD COLUMN assertions TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Add assertion_results column (JSON Vec<AssertionResult>) to history
+    let _ = sqlx::query("ALTER TABLE history ADD COLUMN assertion_results TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Add request_config column (JSON RequestConfig, redacted) to history
+    let _ = sqlx::query("ALTER TABLE history ADD COLUMN request_config TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Add response_file column (path of a body streamed to disk) to history
+    let _ = sqlx::query("ALTER TABLE history ADD COLUMN response_file TEXT")
+        .execute(pool)
+        .await; // Ignore error if column already exists
+
+    // Migration: Make the response columns of history nullable so failed
+    // sends can be recorded. SQLite can't alter constraints, so rebuild the table.
+    let response_required: Option<i64> = sqlx::query_scalar(
+        "SELECT \"notnull\" FROM pragma_table_info('history') WHERE name = 'response_status'",
+    )
+    .fetch_optional(pool)
+    .await?;
+    if response_required == Some(1) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(&history_table_sql("history_new")).execute(&mut *tx).await?;
+        sqlx::query(&format!(
+            "INSERT INTO history_new ({0}) SELECT {0} FROM history",
+            HISTORY_V1_COLUMNS
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DROP TABLE history").execute(&mut *tx).await?;
+        sqlx::query("ALTER TABLE history_new RENAME TO history").execute(&mut *tx).await?;
+        // Rows got new rowids, so the search index is rebuilt below
+        sqlx::query("DROP TABLE IF EXISTS history_fts").execute(&mut *tx).await?;
+        tx.commit().await?;
+    }
+
+    // Indexes for request timelines and for listing and pruning a workspace's
+    // history, newest first. Created after the rebuild above, which drops them.
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_history_request_created ON history(request_id, created_at)")
+        .execute(pool)
+        .await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_history_workspace_created ON history(workspace_id, created_at)")
+        .execute(pool)
+        .await?;
+
+    // Migration: Full-text index over history URLs, bodies and errors. It's
+    // an external-content table reading from `history` by rowid, kept in sync
+    // by triggers. Rows that predate the index are added once.
+    let fts_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'history_fts')",
+    )
+    .fetch_one(pool)
+    .await?;
+    sqlx::query(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+            {}, content='history', content_rowid='rowid'
+        )",
+        HISTORY_FTS_COLUMNS
+    ))
+    .execute(pool)
+    .await?;
+    let fts_insert = format!(
+        "INSERT INTO history_fts (rowid, {}) VALUES ({});",
+        HISTORY_FTS_COLUMNS,
+        history_fts_values("new.")
+    );
+    let fts_delete = format!(
+        "INSERT INTO history_fts (history_fts, rowid, {}) VALUES ('delete', {});",
+        HISTORY_FTS_COLUMNS,
+        history_fts_values("old.")
+    );
+    sqlx::query(&format!(
+        "CREATE TRIGGER IF NOT EXISTS history_fts_insert AFTER INSERT ON history BEGIN {} END",
+        fts_insert
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::query(&format!(
+        "CREATE TRIGGER IF NOT EXISTS history_fts_delete AFTER DELETE ON history BEGIN {} END",
+        fts_delete
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::query(&format!(
+        "CREATE TRIGGER IF NOT EXISTS history_fts_update AFTER UPDATE ON history BEGIN {} {} END",
+        fts_delete, fts_insert
+    ))
+    .execute(pool)
+    .await?;
+    if !fts_exists {
+        sqlx::query(&format!(
+            "INSERT INTO history_fts (rowid, {}) SELECT {} FROM history",
+            HISTORY_FTS_COLUMNS,
+            history_fts_values("")
+        ))
+        .execute(pool)
+        .await?;
+    }
+
     // Mark migration as complete
     sqlx::query(
         "INSERT OR IGNORE INTO schema_version (version, applied_at) VALUES (1, datetime('now'))",
@@ -245,7 +502,15 @@ async fn initialize_default_settings(pool: &Pool<Sqlite>) -> Result<(), Box<dyn
         ("theme", "dark"),
         ("ignore_tls_default", "false"),
         ("follow_redirects_default", "true"),
-        ("history_limit", "100"),
+        ("history_limit", "100"),  // Entries kept per workspace
+        ("history_max_age_days", "30"),
+        ("history_max_bytes", "104857600"),  // 100MB of stored text per workspace
+        ("proxy_enabled", "false"),
+        ("proxy_url", ""),
+        ("proxy_username", ""),
+        ("proxy_password", ""),
+        ("proxy_bypass", "localhost,127.0.0.1"),
+        ("history_redact_names", "x-api-key,api-key,api_key,apikey,access_token,refresh_token,client_secret,password,token"),
     ];
 
     for (key, value) in defaults {
@@ -390,14 +655,16 @@ pub async fn save_request(
     params: &str,
     body: &str,
     auth: Option<&str>,
+    scripts: Option<&str>,
+    assertions: Option<&str>,
     sort_order: i64,
 ) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     let now = chrono::Utc::now().timestamp_millis();
 
     sqlx::query(
-        "INSERT INTO request (id, folder_id, collection_id, name, method, url, headers, params, body, auth, sort_order, created_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT INTO request (id, folder_id, collection_id, name, method, url, headers, params, body, auth, scripts, assertions, sort_order, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(id)
     .bind(folder_id)
@@ -409,6 +676,8 @@ pub async fn save_request(
     .bind(params)
     .bind(body)
     .bind(auth)
+    .bind(scripts)
+    .bind(assertions)
     .bind(sort_order)
     .bind(now)
     .execute(&pool)
@@ -426,12 +695,14 @@ pub async fn update_request(
     params: &str,
     body: &str,
     auth: Option<&str>,
+    scripts: Option<&str>,
+    assertions: Option<&str>,
 ) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
     sqlx::query(
         "UPDATE request 
-         SET name = ?, method = ?, url = ?, headers = ?, params = ?, body = ?, auth = ? 
+         SET name = ?, method = ?, url = ?, headers = ?, params = ?, body = ?, auth = ?, scripts = ?, assertions = ? 
          WHERE id = ?"
     )
     .bind(name)
@@ -441,6 +712,8 @@ pub async fn update_request(
     .bind(params)
     .bind(body)
     .bind(auth)
+    .bind(scripts)
+    .bind(assertions)
     .bind(id)
     .execute(&pool)
     .await?;
@@ -448,11 +721,15 @@ pub async fn update_request(
     Ok(())
 }
 
-pub async fn get_requests(collection_id: &str) -> Result<Vec<(String, String, String, String, String, String, String, String, String)>, sqlx::Error> {
+pub type RequestRow = (String, String, String, String, String, String, String, String, String, String, String);
+
+/// (id, name, method, url, folder_id, headers, params, body, auth, scripts, assertions) of a collection's requests
+pub async fn get_requests(collection_id: &str) -> Result<Vec<RequestRow>, sqlx::Error> {
     let pool = get_pool().await?;
 
-    let results = sqlx::query_as::<_, (String, String, String, String, String, String, String, String, String)>(
-        "SELECT id, name, method, url, COALESCE(folder_id, '') as folder_id, headers, params, body, COALESCE(auth, '') as auth
+    let results = sqlx::query_as::<_, RequestRow>(
+        "SELECT id, name, method, url, COALESCE(folder_id, '') as folder_id, headers, params, body, COALESCE(auth, '') as auth,
+                COALESCE(scripts, '') as scripts, COALESCE(assertions, '') as assertions
          FROM request 
          WHERE collection_id = ? 
          ORDER BY sort_order"
@@ -573,18 +850,64 @@ pub async fn upsert_variable(
     key: &str,
     value: &str,
     is_secret: bool,
-) -> Result<(), sqlx::Error> {
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    upsert_scope_variable(VariableScope::Environment, id, environment_id, key, value, is_secret).await
+}
+
+/// Table and owner column holding variables of a scope. Request-local
+/// variables only live for one send, so they have no table.
+fn scope_table(scope: VariableScope) -> Result<(&'static str, &'static str), Box<dyn std::error::Error + Send + Sync>> {
+    match scope {
+        VariableScope::Global => Ok(("global_variable", "workspace_id")),
+        VariableScope::Collection => Ok(("collection_variable", "collection_id")),
+        VariableScope::Folder => Ok(("folder_variable", "folder_id")),
+        VariableScope::Environment => Ok(("variable", "environment_id")),
+        VariableScope::Local => Err("Request-local variables are not stored".into()),
+    }
+}
+
+/// Insert or update a variable of any scope, encrypting secret values
+pub async fn upsert_scope_variable(
+    scope: VariableScope,
+    id: &str,
+    owner_id: &str,
+    key: &str,
+    value: &str,
+    is_secret: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let pool = get_pool().await?;
+    let (table, owner_column) = scope_table(scope)?;
 
-    sqlx::query(
-        "INSERT INTO variable (id, environment_id, key, value, is_secret)
+    // The UI only ever sees the mask for secrets, so keep what is stored
+    let plaintext = if value == secrets::SECRET_MASK {
+        let existing = sqlx::query_as::<_, (String,)>(&format!("SELECT value FROM {} WHERE id = ?", table))
+            .bind(id)
+            .fetch_optional(&pool)
+            .await?;
+        match existing {
+            Some((existing,)) => secrets::decrypt(&existing)?,
+            None => String::new(),
+        }
+    } else {
+        value.to_string()
+    };
+
+    let stored = if is_secret {
+        secrets::encrypt(&plaintext)?
+    } else {
+        plaintext
+    };
+
+    sqlx::query(&format!(
+        "INSERT INTO {} (id, {}, key, value, is_secret)
          VALUES (?, ?, ?, ?, ?)
-         ON CONFLICT(id) DO UPDATE SET key = excluded.key, value = excluded.value, is_secret = excluded.is_secret"
-    )
+         ON CONFLICT(id) DO UPDATE SET key = excluded.key, value = excluded.value, is_secret = excluded.is_secret",
+        table, owner_column
+    ))
     .bind(id)
-    .bind(environment_id)
+    .bind(owner_id)
     .bind(key)
-    .bind(value)
+    .bind(stored)
     .bind(is_secret as i32)
     .execute(&pool)
     .await?;
@@ -592,122 +915,789 @@ pub async fn upsert_variable(
     Ok(())
 }
 
-pub async fn get_variables(environment_id: &str) -> Result<Vec<(String, String, String, bool)>, sqlx::Error> {
+/// Get variables of an environment; secret values are returned as stored (encrypted)
+pub async fn get_variables(environment_id: &str) -> Result<Vec<(String, String, String, bool)>, Box<dyn std::error::Error + Send + Sync>> {
+    get_scope_variables(VariableScope::Environment, environment_id).await
+}
+
+/// Get (id, key, stored value, is_secret) of the variables owned by a workspace,
+/// collection, folder or environment
+pub async fn get_scope_variables(
+    scope: VariableScope,
+    owner_id: &str,
+) -> Result<Vec<(String, String, String, bool)>, Box<dyn std::error::Error + Send + Sync>> {
     let pool = get_pool().await?;
+    let (table, owner_column) = scope_table(scope)?;
 
-    let results = sqlx::query_as::<_, (String, String, String, bool)>(
-        "SELECT id, key, value, is_secret FROM variable WHERE environment_id = ?"
-    )
-    .bind(environment_id)
+    let results = sqlx::query_as::<_, (String, String, String, bool)>(&format!(
+        "SELECT id, key, value, is_secret FROM {} WHERE {} = ? ORDER BY rowid",
+        table, owner_column
+    ))
+    .bind(owner_id)
     .fetch_all(&pool)
     .await?;
 
     Ok(results)
 }
 
-pub async fn get_active_variables(workspace_id: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+pub async fn delete_scope_variable(scope: VariableScope, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let pool = get_pool().await?;
+    let (table, _) = scope_table(scope)?;
 
-    // Fetch variables for the active environment in this workspace
-    let results = sqlx::query_as::<_, (String, String)>(
-        "SELECT v.key, v.value 
-         FROM variable v 
-         JOIN environment e ON v.environment_id = e.id 
-         WHERE e.workspace_id = ? AND e.is_active = 1"
+    sqlx::query(&format!("DELETE FROM {} WHERE id = ?", table))
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Set a variable from a script: updates the key in the active environment,
+/// or in the workspace globals when no environment is active. Returns whether
+/// the variable is secret.
+pub async fn set_active_variable(
+    workspace_id: &str,
+    key: &str,
+    value: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = get_pool().await?;
+
+    let active_env = sqlx::query_as::<_, (String,)>(
+        "SELECT id FROM environment WHERE workspace_id = ? AND is_active = 1"
     )
     .bind(workspace_id)
-    .fetch_all(&pool)
+    .fetch_optional(&pool)
     .await?;
 
-    Ok(results)
+    let (scope, owner_id) = match active_env {
+        Some((environment_id,)) => (VariableScope::Environment, environment_id),
+        None => (VariableScope::Global, workspace_id.to_string()),
+    };
+
+    let existing = get_scope_variables(scope, &owner_id)
+        .await?
+        .into_iter()
+        .find(|(_, k, _, _)| k == key);
+    let (id, is_secret) = match existing {
+        Some((id, _, _, is_secret)) => (id, is_secret),
+        None => (uuid::Uuid::new_v4().to_string(), false),
+    };
+
+    upsert_scope_variable(scope, &id, &owner_id, key, value, is_secret).await?;
+    Ok(is_secret)
 }
 
-/// Clear all user data (workspaces, collections, history, environments, etc.) and reset settings to defaults.
-pub async fn clear_all_data() -> Result<(), Box<dyn std::error::Error>> {
+/// Collection and enclosing folders (innermost first) of a saved request
+pub async fn get_request_location(request_id: &str) -> Result<VariableLocation, sqlx::Error> {
     let pool = get_pool().await?;
 
-    // Delete in order respecting foreign keys
-    sqlx::query("DELETE FROM variable").execute(&pool).await?;
-    sqlx::query("DELETE FROM environment").execute(&pool).await?;
-    sqlx::query("DELETE FROM history").execute(&pool).await?;
-    sqlx::query("DELETE FROM tab_state").execute(&pool).await?;
-    sqlx::query("DELETE FROM request").execute(&pool).await?;
-    sqlx::query("DELETE FROM folder").execute(&pool).await?;
-    sqlx::query("DELETE FROM collection").execute(&pool).await?;
-    sqlx::query("DELETE FROM workspace").execute(&pool).await?;
-    sqlx::query("DELETE FROM settings").execute(&pool).await?;
+    let row = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT collection_id, folder_id FROM request WHERE id = ?"
+    )
+    .bind(request_id)
+    .fetch_optional(&pool)
+    .await?;
 
-    initialize_default_settings(&pool).await?;
-    Ok(())
+    let Some((collection_id, mut folder_id)) = row else {
+        return Ok(VariableLocation::default());
+    };
+
+    let mut folder_ids = Vec::new();
+    while let Some(id) = folder_id.filter(|id| !id.is_empty() && !folder_ids.contains(id)) {
+        folder_id = sqlx::query_as::<_, (Option<String>,)>("SELECT parent_folder_id FROM folder WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&pool)
+            .await?
+            .and_then(|(parent,)| parent);
+        folder_ids.push(id);
+    }
+
+    Ok(VariableLocation {
+        collection_id: Some(collection_id),
+        folder_ids,
+    })
 }
 
-// Delete operations
-pub async fn delete_workspace(id: &str) -> Result<(), sqlx::Error> {
+/// Get the active environment's variables with secrets decrypted, for sending requests
+pub async fn get_active_variables(
+    workspace_id: &str,
+    location: &VariableLocation,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut variables = Vec::new();
+    for variable in list_active_variables(workspace_id, location).await? {
+        let value = if variable.is_secret { secrets::decrypt(&variable.value)? } else { variable.value };
+        variables.push((variable.key, value));
+    }
+
+    Ok(variables)
+}
+
+/// Get the decrypted values of the active secret variables
+pub async fn get_active_secret_values(
+    workspace_id: &str,
+    location: &VariableLocation,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut values = Vec::new();
+    for variable in list_active_variables(workspace_id, location).await? {
+        if variable.is_secret {
+            values.push(secrets::decrypt(&variable.value)?);
+        }
+    }
+
+    Ok(values)
+}
+
+/// Resolve the variables visible to a request without decrypting secrets.
+/// Precedence: innermost folder, outer folders, collection, active
+/// environment, then workspace globals; the first definition of a key wins.
+pub async fn list_active_variables(
+    workspace_id: &str,
+    location: &VariableLocation,
+) -> Result<Vec<ResolvedVariable>, Box<dyn std::error::Error + Send + Sync>> {
     let pool = get_pool().await?;
-    
-    // 1. Delete variables (linked via environments)
-    sqlx::query("DELETE FROM variable WHERE environment_id IN (SELECT id FROM environment WHERE workspace_id = ?)")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-        
-    // 2. Delete environments
-    sqlx::query("DELETE FROM environment WHERE workspace_id = ?")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-        
-    // 3. Delete requests
-    // Re-check schema for request... it HAS collection_id.
-    sqlx::query("DELETE FROM request WHERE collection_id IN (SELECT id FROM collection WHERE workspace_id = ?)")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-        
-    // 4. Delete folders
-    sqlx::query("DELETE FROM folder WHERE collection_id IN (SELECT id FROM collection WHERE workspace_id = ?)")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-        
-    // 5. Delete collections
-    sqlx::query("DELETE FROM collection WHERE workspace_id = ?")
-        .bind(id)
-        .execute(&pool)
-        .await?;
-        
-    // 6. Delete workspace itself
-    sqlx::query("DELETE FROM workspace WHERE id = ?")
-        .bind(id)
-        .execute(&pool)
+
+    let mut layers: Vec<(VariableScope, String)> = location
+        .folder_ids
+        .iter()
+        .map(|id| (VariableScope::Folder, id.clone()))
+        .collect();
+    if let Some(collection_id) = &location.collection_id {
+        layers.push((VariableScope::Collection, collection_id.clone()));
+    }
+    let active_env = sqlx::query_as::<_, (String,)>(
+        "SELECT id FROM environment WHERE workspace_id = ? AND is_active = 1"
+    )
+    .bind(workspace_id)
+    .fetch_optional(&pool)
+    .await?;
+    if let Some((environment_id,)) = active_env {
+        layers.push((VariableScope::Environment, environment_id));
+    }
+    layers.push((VariableScope::Global, workspace_id.to_string()));
+
+    let mut resolved: Vec<ResolvedVariable> = Vec::new();
+    for (scope, owner_id) in layers {
+        for (_, key, value, is_secret) in get_scope_variables(scope, &owner_id).await? {
+            if key.is_empty() || resolved.iter().any(|v| v.key == key) {
+                continue;
+            }
+            resolved.push(ResolvedVariable {
+                key,
+                value,
+                is_secret,
+                scope,
+                source_id: owner_id.clone(),
+            });
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Columns holding values sealed with the secrets key, as (table, column, filter)
+const SECRET_COLUMNS: [(&str, &str, &str); 8] = [
+    ("variable", "value", "is_secret = 1"),
+    ("global_variable", "value", "is_secret = 1"),
+    ("collection_variable", "value", "is_secret = 1"),
+    ("folder_variable", "value", "is_secret = 1"),
+    ("oauth_token", "access_token", "1"),
+    ("oauth_token", "refresh_token", "refresh_token IS NOT NULL"),
+    ("settings", "value", "key = 'proxy_password' AND value != ''"),
+    ("host_certificate", "passphrase", "passphrase IS NOT NULL AND passphrase != ''"),
+];
+
+/// A value sealed with the secrets key: (table, column, rowid, stored value)
+pub type StoredSecret = (&'static str, &'static str, i64, String);
+
+/// Get every value sealed with the secrets key: secret variables of any scope,
+/// cached OAuth tokens, the proxy password and client certificate passphrases
+pub async fn get_secret_values() -> Result<Vec<StoredSecret>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let mut values = Vec::new();
+    for (table, column, filter) in SECRET_COLUMNS {
+        let rows = sqlx::query_as::<_, (i64, String)>(&format!(
+            "SELECT rowid, {} FROM {} WHERE {}",
+            column, table, filter
+        ))
+        .fetch_all(&pool)
         .await?;
-        
+        values.extend(rows.into_iter().map(|(rowid, value)| (table, column, rowid, value)));
+    }
+
+    Ok(values)
+}
+
+/// Store re-encrypted secret values. `settings` are written in the same
+/// transaction, so a key change and the values sealed with it land together.
+pub async fn update_secret_values(values: &[StoredSecret], settings: &[(&str, String)]) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+    let mut tx = pool.begin().await?;
+
+    for (table, column, rowid, value) in values {
+        sqlx::query(&format!("UPDATE {} SET {} = ? WHERE rowid = ?", table, column))
+            .bind(value)
+            .bind(rowid)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for (key, value) in settings {
+        sqlx::query("INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)")
+            .bind(key)
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn update_workspace_name(id: &str, name: &str) -> Result<(), sqlx::Error> {
+// Cookie operations
+type CookieRow = (
+    String, String, String, String, String, String,
+    Option<i64>, bool, bool, bool, Option<String>, i64,
+);
+
+fn cookie_from_row(row: CookieRow) -> Cookie {
+    let (
+        id, workspace_id, name, value, domain, path,
+        expires_at, secure, http_only, host_only, same_site, created_at,
+    ) = row;
+
+    Cookie {
+        id,
+        workspace_id,
+        name,
+        value,
+        domain,
+        path,
+        expires_at,
+        secure,
+        http_only,
+        host_only,
+        same_site,
+        created_at,
+    }
+}
+
+/// Get all unexpired cookies for a workspace, persisted and session ones,
+/// purging expired ones first
+pub async fn get_cookies(workspace_id: &str) -> Result<Vec<Cookie>, sqlx::Error> {
     let pool = get_pool().await?;
     let now = chrono::Utc::now().timestamp_millis();
 
-    sqlx::query("UPDATE workspace SET name = ?, updated_at = ? WHERE id = ?")
-        .bind(name)
+    sqlx::query("DELETE FROM cookie WHERE workspace_id = ? AND expires_at IS NOT NULL AND expires_at <= ?")
+        .bind(workspace_id)
         .bind(now)
-        .bind(id)
         .execute(&pool)
         .await?;
 
-    Ok(())
+    let rows = sqlx::query_as::<_, CookieRow>(
+        "SELECT id, workspace_id, name, value, domain, path, expires_at, secure, http_only, host_only, same_site, created_at
+         FROM cookie
+         WHERE workspace_id = ?
+         ORDER BY domain, path, name"
+    )
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await?;
+
+    let mut all: Vec<Cookie> = rows.into_iter().map(cookie_from_row).collect();
+    all.extend(cookies::session_cookies(workspace_id));
+    all.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+
+    Ok(all)
+}
+
+/// Save a cookie edited by hand. These are always persisted, even without
+/// an expiry date.
+pub async fn upsert_cookie(cookie: &Cookie) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+    upsert_cookie_internal(&mut *pool.acquire().await?, cookie).await?;
+    cookies::forget_session_cookie(&cookie.id);
+    Ok(())
+}
+
+async fn upsert_cookie_internal(conn: &mut sqlx::SqliteConnection, cookie: &Cookie) -> Result<(), sqlx::Error> {
+    // A cookie is identified by (name, domain, path), so replace any row with the same key
+    sqlx::query(
+        "DELETE FROM cookie WHERE workspace_id = ? AND name = ? AND domain = ? AND path = ? AND id != ?"
+    )
+    .bind(&cookie.workspace_id)
+    .bind(&cookie.name)
+    .bind(&cookie.domain)
+    .bind(&cookie.path)
+    .bind(&cookie.id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO cookie (id, workspace_id, name, value, domain, path, expires_at, secure, http_only, host_only, same_site, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name, value = excluded.value, domain = excluded.domain, path = excluded.path,
+            expires_at = excluded.expires_at, secure = excluded.secure, http_only = excluded.http_only,
+            host_only = excluded.host_only, same_site = excluded.same_site"
+    )
+    .bind(&cookie.id)
+    .bind(&cookie.workspace_id)
+    .bind(&cookie.name)
+    .bind(&cookie.value)
+    .bind(&cookie.domain)
+    .bind(&cookie.path)
+    .bind(cookie.expires_at)
+    .bind(cookie.secure as i32)
+    .bind(cookie.http_only as i32)
+    .bind(cookie.host_only as i32)
+    .bind(&cookie.same_site)
+    .bind(cookie.created_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Persist the cookies a send created, updated or expired. Session cookies
+/// are kept in memory only.
+pub async fn save_cookie_jar(jar: &CookieJar) -> Result<(), sqlx::Error> {
+    if jar.changed().is_empty() && jar.removed().is_empty() {
+        return Ok(());
+    }
+
+    let pool = get_pool().await?;
+    let mut tx = pool.begin().await?;
+
+    for id in jar.removed() {
+        sqlx::query("DELETE FROM cookie WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for cookie in jar.changed().iter().filter(|c| c.expires_at.is_some()) {
+        upsert_cookie_internal(&mut tx, cookie).await?;
+    }
+
+    // A session cookie replaces any persisted cookie with the same key
+    for cookie in jar.changed().iter().filter(|c| c.expires_at.is_none()) {
+        sqlx::query("DELETE FROM cookie WHERE workspace_id = ? AND name = ? AND domain = ? AND path = ?")
+            .bind(&cookie.workspace_id)
+            .bind(&cookie.name)
+            .bind(&cookie.domain)
+            .bind(&cookie.path)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    for id in jar.removed() {
+        cookies::forget_session_cookie(id);
+    }
+    for cookie in jar.changed() {
+        match cookie.expires_at {
+            Some(_) => cookies::forget_session_cookie(&cookie.id),
+            None => cookies::keep_session_cookie(cookie),
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn delete_cookie(id: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query("DELETE FROM cookie WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    cookies::forget_session_cookie(id);
+
+    Ok(())
+}
+
+pub async fn clear_cookies(workspace_id: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query("DELETE FROM cookie WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .execute(&pool)
+        .await?;
+    cookies::clear_session_cookies(Some(workspace_id));
+
+    Ok(())
+}
+
+// Host certificate operations
+/// Get all host certificate entries, with passphrases as stored (encrypted)
+pub async fn get_host_certificates() -> Result<Vec<HostCertificate>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let rows = sqlx::query_as::<_, (
+        String, String, String, Option<String>, Option<String>,
+        Option<String>, Option<String>, bool, i64
+    )>(
+        "SELECT id, host_pattern, cert_format, cert_path, key_path, passphrase, ca_path, enabled, created_at
+         FROM host_certificate
+         ORDER BY created_at"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, host_pattern, cert_format, cert_path, key_path, passphrase, ca_path, enabled, created_at)| {
+            HostCertificate {
+                id,
+                host_pattern,
+                cert_format,
+                cert_path,
+                key_path,
+                passphrase,
+                ca_path,
+                enabled,
+                created_at,
+            }
+        })
+        .collect())
+}
+
+/// Insert or update a host certificate entry, encrypting its passphrase
+pub async fn upsert_host_certificate(cert: &HostCertificate) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let pool = get_pool().await?;
+
+    // The UI only ever sees the mask for passphrases, so keep what is stored
+    let passphrase = match cert.passphrase.as_deref() {
+        Some(secrets::SECRET_MASK) => {
+            sqlx::query_scalar::<_, Option<String>>("SELECT passphrase FROM host_certificate WHERE id = ?")
+                .bind(&cert.id)
+                .fetch_optional(&pool)
+                .await?
+                .flatten()
+        }
+        Some(passphrase) if !passphrase.is_empty() => Some(secrets::encrypt(passphrase)?),
+        _ => None,
+    };
+
+    sqlx::query(
+        "INSERT INTO host_certificate (id, host_pattern, cert_format, cert_path, key_path, passphrase, ca_path, enabled, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET
+            host_pattern = excluded.host_pattern, cert_format = excluded.cert_format,
+            cert_path = excluded.cert_path, key_path = excluded.key_path,
+            passphrase = excluded.passphrase, ca_path = excluded.ca_path, enabled = excluded.enabled"
+    )
+    .bind(&cert.id)
+    .bind(&cert.host_pattern)
+    .bind(&cert.cert_format)
+    .bind(&cert.cert_path)
+    .bind(&cert.key_path)
+    .bind(&passphrase)
+    .bind(&cert.ca_path)
+    .bind(cert.enabled as i32)
+    .bind(cert.created_at)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_host_certificate(id: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query("DELETE FROM host_certificate WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+// OAuth 2.0 token cache operations
+/// Get a cached token, decrypting its access and refresh tokens
+pub async fn get_oauth_token(
+    workspace_id: &str,
+    cache_key: &str,
+) -> Result<Option<OAuth2Token>, Box<dyn std::error::Error + Send + Sync>> {
+    let pool = get_pool().await?;
+
+    let row = sqlx::query_as::<_, (
+        String, String, String, Option<String>, String, Option<String>, Option<i64>, i64
+    )>(
+        "SELECT workspace_id, cache_key, access_token, refresh_token, token_type, scope, expires_at, created_at
+         FROM oauth_token
+         WHERE workspace_id = ? AND cache_key = ?"
+    )
+    .bind(workspace_id)
+    .bind(cache_key)
+    .fetch_optional(&pool)
+    .await?;
+
+    let Some((workspace_id, cache_key, access_token, refresh_token, token_type, scope, expires_at, created_at)) = row
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(OAuth2Token {
+        workspace_id,
+        cache_key,
+        access_token: secrets::decrypt(&access_token)?,
+        refresh_token: refresh_token.as_deref().map(secrets::decrypt).transpose()?,
+        token_type,
+        scope,
+        expires_at,
+        created_at,
+    }))
+}
+
+/// Cache a token; the access and refresh tokens are stored encrypted
+pub async fn save_oauth_token(token: &OAuth2Token) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let pool = get_pool().await?;
+    let access_token = secrets::encrypt(&token.access_token)?;
+    let refresh_token = token.refresh_token.as_deref().map(secrets::encrypt).transpose()?;
+
+    sqlx::query(
+        "INSERT INTO oauth_token (workspace_id, cache_key, access_token, refresh_token, token_type, scope, expires_at, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT(workspace_id, cache_key) DO UPDATE SET
+            access_token = excluded.access_token, refresh_token = excluded.refresh_token,
+            token_type = excluded.token_type, scope = excluded.scope,
+            expires_at = excluded.expires_at, created_at = excluded.created_at"
+    )
+    .bind(&token.workspace_id)
+    .bind(&token.cache_key)
+    .bind(&access_token)
+    .bind(&refresh_token)
+    .bind(&token.token_type)
+    .bind(&token.scope)
+    .bind(token.expires_at)
+    .bind(token.created_at)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_oauth_token(workspace_id: &str, cache_key: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query("DELETE FROM oauth_token WHERE workspace_id = ? AND cache_key = ?")
+        .bind(workspace_id)
+        .bind(cache_key)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn clear_oauth_tokens(workspace_id: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query("DELETE FROM oauth_token WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+// GraphQL schema cache operations
+pub async fn get_graphql_schema(request_id: &str) -> Result<Option<GraphQLSchema>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let row = sqlx::query_as::<_, (String, String, String, i64)>(
+        "SELECT request_id, url, schema, fetched_at FROM graphql_schema WHERE request_id = ?"
+    )
+    .bind(request_id)
+    .fetch_optional(&pool)
+    .await?;
+
+    Ok(row.map(|(request_id, url, schema, fetched_at)| GraphQLSchema {
+        request_id,
+        url,
+        schema: serde_json::from_str(&schema).unwrap_or_default(),
+        fetched_at,
+    }))
+}
+
+pub async fn save_graphql_schema(schema: &GraphQLSchema) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+
+    sqlx::query(
+        "INSERT INTO graphql_schema (request_id, url, schema, fetched_at)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT(request_id) DO UPDATE SET
+            url = excluded.url, schema = excluded.schema, fetched_at = excluded.fetched_at"
+    )
+    .bind(&schema.request_id)
+    .bind(&schema.url)
+    .bind(schema.schema.to_string())
+    .bind(schema.fetched_at)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Clear all user data (workspaces, collections, history, environments, etc.) and reset settings to defaults.
+pub async fn clear_all_data() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = get_pool().await?;
+
+    // Delete in order respecting foreign keys
+    sqlx::query("DELETE FROM cookie").execute(&pool).await?;
+    cookies::clear_session_cookies(None);
+    sqlx::query("DELETE FROM oauth_token").execute(&pool).await?;
+    sqlx::query("DELETE FROM host_certificate").execute(&pool).await?;
+    sqlx::query("DELETE FROM variable").execute(&pool).await?;
+    sqlx::query("DELETE FROM global_variable").execute(&pool).await?;
+    sqlx::query("DELETE FROM collection_variable").execute(&pool).await?;
+    sqlx::query("DELETE FROM folder_variable").execute(&pool).await?;
+    sqlx::query("DELETE FROM environment").execute(&pool).await?;
+    let files = sqlx::query_scalar("DELETE FROM history RETURNING response_file")
+        .fetch_all(&pool)
+        .await?;
+    remove_response_files(files).await;
+    sqlx::query("DELETE FROM tab_state").execute(&pool).await?;
+    sqlx::query("DELETE FROM graphql_schema").execute(&pool).await?;
+    sqlx::query("DELETE FROM request").execute(&pool).await?;
+    sqlx::query("DELETE FROM folder").execute(&pool).await?;
+    sqlx::query("DELETE FROM collection").execute(&pool).await?;
+    sqlx::query("DELETE FROM workspace").execute(&pool).await?;
+    sqlx::query("DELETE FROM settings").execute(&pool).await?;
+
+    initialize_default_settings(&pool).await?;
+    Ok(())
+}
+
+// Delete operations
+pub async fn delete_workspace(id: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+    
+    // 1. Delete variables (linked via environments)
+    sqlx::query("DELETE FROM variable WHERE environment_id IN (SELECT id FROM environment WHERE workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    // 2. Delete environments
+    sqlx::query("DELETE FROM environment WHERE workspace_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    // 2a. Delete global, collection and folder variables
+    sqlx::query("DELETE FROM global_variable WHERE workspace_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("DELETE FROM collection_variable WHERE collection_id IN (SELECT id FROM collection WHERE workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("DELETE FROM folder_variable WHERE folder_id IN (SELECT f.id FROM folder f JOIN collection c ON f.collection_id = c.id WHERE c.workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    // 2b. Delete history and its response files
+    let files = sqlx::query_scalar("DELETE FROM history WHERE workspace_id = ? RETURNING response_file")
+        .bind(id)
+        .fetch_all(&pool)
+        .await?;
+    remove_response_files(files).await;
+
+    // 2c. Delete cookies and cached OAuth tokens
+    sqlx::query("DELETE FROM cookie WHERE workspace_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    cookies::clear_session_cookies(Some(id));
+
+    sqlx::query("DELETE FROM oauth_token WHERE workspace_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    // 3. Delete requests and their cached GraphQL schemas
+    // Re-check schema for request... it HAS collection_id.
+    sqlx::query("DELETE FROM graphql_schema WHERE request_id IN (SELECT r.id FROM request r JOIN collection c ON r.collection_id = c.id WHERE c.workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("DELETE FROM request WHERE collection_id IN (SELECT id FROM collection WHERE workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    // 4. Delete folders
+    sqlx::query("DELETE FROM folder WHERE collection_id IN (SELECT id FROM collection WHERE workspace_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    // 5. Delete collections
+    sqlx::query("DELETE FROM collection WHERE workspace_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    // 6. Delete workspace itself
+    sqlx::query("DELETE FROM workspace WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+        
+    Ok(())
+}
+
+pub async fn update_workspace_name(id: &str, name: &str) -> Result<(), sqlx::Error> {
+    let pool = get_pool().await?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    sqlx::query("UPDATE workspace SET name = ?, updated_at = ? WHERE id = ?")
+        .bind(name)
+        .bind(now)
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
 }
 
 pub async fn delete_collection(id: &str) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
     // Delete requests in this collection
+    sqlx::query("DELETE FROM graphql_schema WHERE request_id IN (SELECT id FROM request WHERE collection_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("UPDATE history SET request_id = NULL WHERE request_id IN (SELECT id FROM request WHERE collection_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
     sqlx::query("DELETE FROM request WHERE collection_id = ?")
         .bind(id)
         .execute(&pool)
         .await?;
         
+    // Delete collection and folder variables
+    sqlx::query("DELETE FROM collection_variable WHERE collection_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("DELETE FROM folder_variable WHERE folder_id IN (SELECT id FROM folder WHERE collection_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
     // Delete folders in this collection
     sqlx::query("DELETE FROM folder WHERE collection_id = ?")
         .bind(id)
@@ -727,11 +1717,26 @@ pub async fn delete_folder(id: &str) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
     // Move requests to root of collection or delete them? Usually delete is safer for simple MVP
+    sqlx::query("DELETE FROM graphql_schema WHERE request_id IN (SELECT id FROM request WHERE folder_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    sqlx::query("UPDATE history SET request_id = NULL WHERE request_id IN (SELECT id FROM request WHERE folder_id = ?)")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
     sqlx::query("DELETE FROM request WHERE folder_id = ?")
         .bind(id)
         .execute(&pool)
         .await?;
         
+    sqlx::query("DELETE FROM folder_variable WHERE folder_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
     // Delete folder
     sqlx::query("DELETE FROM folder WHERE id = ?")
         .bind(id)
@@ -744,6 +1749,17 @@ pub async fn delete_folder(id: &str) -> Result<(), sqlx::Error> {
 pub async fn delete_request(id: &str) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
+    sqlx::query("DELETE FROM graphql_schema WHERE request_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
+    // Keep the request's history, unlinked
+    sqlx::query("UPDATE history SET request_id = NULL WHERE request_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+
     sqlx::query("DELETE FROM request WHERE id = ?")
         .bind(id)
         .execute(&pool)
@@ -753,6 +1769,8 @@ pub async fn delete_request(id: &str) -> Result<(), sqlx::Error> {
 }
 
 // History operations
+// `request_id` links a row to the saved request it was sent from; unknown ids
+// (e.g. a request deleted while in flight) are stored as NULL.
 pub async fn add_history_entry(
     id: &str,
     workspace_id: &str,
@@ -769,8 +1787,12 @@ pub async fn add_history_entry(
     res_body_type: &str,
     res_size: i64,
     res_time: i64,
+    res_timing: Option<&str>,
     content_type: Option<&str>,
     content_encoding: Option<&str>,
+    assertion_results: Option<&str>,
+    req_config: Option<&str>,
+    response_file: Option<&str>,
 ) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     let now = chrono::Utc::now().timestamp_millis();
@@ -781,9 +1803,9 @@ pub async fn add_history_entry(
             request_headers, request_params, request_body, 
             response_status, response_headers, 
             response_body_preview, response_body_full, response_body_type,
-            response_size_bytes, response_time_ms, 
-            content_type, content_encoding, created_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            response_size_bytes, response_time_ms, response_timing,
+            content_type, content_encoding, assertion_results, request_config, response_file, created_at
+        ) VALUES (?, ?, (SELECT id FROM request WHERE id = ?), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(id)
     .bind(workspace_id)
@@ -800,8 +1822,12 @@ pub async fn add_history_entry(
     .bind(res_body_type)
     .bind(res_size)
     .bind(res_time)
+    .bind(res_timing)
     .bind(content_type)
     .bind(content_encoding)
+    .bind(assertion_results)
+    .bind(req_config)
+    .bind(response_file)
     .bind(now)
     .execute(&pool)
     .await?;
@@ -809,67 +1835,404 @@ pub async fn add_history_entry(
     Ok(())
 }
 
-pub async fn get_history(workspace_id: &str, limit: i64, offset: i64) -> Result<Vec<crate::models::HistoryItem>, sqlx::Error> {
+/// Record a send that failed before a response arrived
+pub async fn add_failed_history_entry(
+    id: &str,
+    workspace_id: &str,
+    request_id: Option<&str>,
+    method: &str,
+    url: &str,
+    req_headers: &str,
+    req_params: &str,
+    req_body: Option<&str>,
+    req_config: Option<&str>,
+    error_kind: &str,
+    error_message: Option<&str>,
+) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
+    let now = chrono::Utc::now().timestamp_millis();
 
-    let rows = sqlx::query_as::<_, (
-        String, String, Option<String>, String, String,
-        String, String, Option<String>,
-        i64, String,
-        i64, i64, i64
-    )>(
+    sqlx::query(
+        "INSERT INTO history (
+            id, workspace_id, request_id, method, url,
+            request_headers, request_params, request_body, request_config,
+            error_kind, error_message, created_at
+        ) VALUES (?, ?, (SELECT id FROM request WHERE id = ?), ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(id)
+    .bind(workspace_id)
+    .bind(request_id)
+    .bind(method)
+    .bind(url)
+    .bind(req_headers)
+    .bind(req_params)
+    .bind(req_body)
+    .bind(req_config)
+    .bind(error_kind)
+    .bind(error_message)
+    .bind(now)
+    .execute(&pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Columns selected into a `HistoryItem`
+const HISTORY_ITEM_COLUMNS: &str = "id, workspace_id, request_id, method, url, \
+    request_headers, request_params, request_body, response_status, response_headers, \
+    response_time_ms, response_timing, response_size_bytes, assertion_results, \
+    error_kind, error_message, created_at";
+
+fn history_item_from_row(row: &SqliteRow) -> crate::models::HistoryItem {
+    crate::models::HistoryItem {
+        id: row.get("id"),
+        workspace_id: row.get("workspace_id"),
+        request_id: row.get("request_id"),
+        method: row.get("method"),
+        url: row.get("url"),
+        request_headers: row.get("request_headers"),
+        request_params: row.get("request_params"),
+        request_body: row.get("request_body"),
+        response_status: row.get::<Option<i64>, _>("response_status").map(|s| s as u16),
+        response_headers: row.get("response_headers"),
+        response_time_ms: row.get::<Option<i64>, _>("response_time_ms").map(|t| t as u64),
+        response_timing: row.get("response_timing"),
+        response_size_bytes: row.get::<Option<i64>, _>("response_size_bytes").map(|s| s as usize),
+        assertion_results: row.get("assertion_results"),
+        error_kind: row.get("error_kind"),
+        error_message: row.get("error_message"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_history(
+    workspace_id: &str,
+    limit: i64,
+    offset: i64,
+    outcome: Option<HistoryOutcome>,
+) -> Result<Vec<crate::models::HistoryItem>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let outcome_filter = match outcome {
+        Some(HistoryOutcome::Success) => "AND error_kind IS NULL",
+        Some(HistoryOutcome::Failed) => "AND error_kind IS NOT NULL",
+        None => "",
+    };
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT 
-            id, workspace_id, request_id, method, url, 
-            request_headers, request_params, request_body, 
-            response_status, response_headers, 
-            response_time_ms, response_size_bytes, created_at
+        SELECT {}
         FROM history 
-        WHERE workspace_id = ? 
+        WHERE workspace_id = ? {}
         ORDER BY created_at DESC 
         LIMIT ? OFFSET ?
-        "#
-    )
+        "#,
+        HISTORY_ITEM_COLUMNS, outcome_filter
+    ))
     .bind(workspace_id)
     .bind(limit)
     .bind(offset)
     .fetch_all(&pool)
     .await?;
 
-    let items = rows.into_iter().map(|(
-        id, workspace_id, request_id, method, url,
-        request_headers, request_params, request_body,
-        response_status, response_headers,
-        response_time_ms, response_size_bytes, created_at
-    )| {
-        crate::models::HistoryItem {
-            id,
-            workspace_id,
-            request_id,
-            method,
-            url,
-            request_headers,
-            request_params,
-            request_body,
-            response_status: response_status as u16,
-            response_headers,
-            response_time_ms: response_time_ms as u64,
-            response_size_bytes: response_size_bytes as usize,
-            created_at,
+    Ok(rows.iter().map(history_item_from_row).collect())
+}
+
+/// Search history, newest first. `url_regex` is the compiled
+/// `filter.url_pattern`; it is applied after the SQL filters.
+pub async fn query_history(
+    workspace_id: &str,
+    filter: &HistoryFilter,
+    url_regex: Option<&Regex>,
+) -> Result<Vec<crate::models::HistoryItem>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    // The regex runs in Rust, so it scans only ids and URLs and the page of
+    // matches is loaded by id afterwards
+    let columns = if url_regex.is_some() { "id, url" } else { HISTORY_ITEM_COLUMNS };
+    let mut query = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM history WHERE workspace_id = ",
+        columns
+    ));
+    query.push_bind(workspace_id);
+
+    if !filter.methods.is_empty() {
+        query.push(" AND method IN (");
+        let mut methods = query.separated(", ");
+        for method in &filter.methods {
+            methods.push_bind(method.to_uppercase());
         }
-    }).collect();
+        query.push(")");
+    }
+    if let Some(min) = filter.status_min {
+        query.push(" AND response_status >= ").push_bind(min as i64);
+    }
+    if let Some(max) = filter.status_max {
+        query.push(" AND response_status <= ").push_bind(max as i64);
+    }
+    if let Some(text) = filter.url_contains.as_deref().filter(|t| !t.is_empty()) {
+        query.push(" AND instr(lower(url), lower(").push_bind(text).push(")) > 0");
+    }
+    if let Some(since) = filter.since {
+        query.push(" AND created_at >= ").push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        query.push(" AND created_at < ").push_bind(until);
+    }
+    if let Some(min) = filter.min_time_ms {
+        query.push(" AND response_time_ms >= ").push_bind(min as i64);
+    }
+    if let Some(max) = filter.max_time_ms {
+        query.push(" AND response_time_ms <= ").push_bind(max as i64);
+    }
+    if let Some(request_id) = &filter.request_id {
+        query.push(" AND request_id = ").push_bind(request_id);
+    }
+    match filter.outcome {
+        Some(HistoryOutcome::Success) => {
+            query.push(" AND error_kind IS NULL");
+        }
+        Some(HistoryOutcome::Failed) => {
+            query.push(" AND error_kind IS NOT NULL");
+        }
+        None => {}
+    }
+    if let Some(text) = filter.text.as_deref().map(fts_query).filter(|q| !q.is_empty()) {
+        query
+            .push(" AND rowid IN (SELECT rowid FROM history_fts WHERE history_fts MATCH ")
+            .push_bind(text)
+            .push(")");
+    }
+
+    query.push(" ORDER BY created_at DESC");
+    let limit = filter.limit.unwrap_or(100);
+    let offset = filter.offset.unwrap_or(0);
+    let Some(regex) = url_regex else {
+        query.push(" LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+        let rows = query.build().fetch_all(&pool).await?;
+        return Ok(rows.iter().map(history_item_from_row).collect());
+    };
+
+    let ids: Vec<String> = query
+        .build_query_as::<(String, String)>()
+        .fetch_all(&pool)
+        .await?
+        .into_iter()
+        .filter(|(_, url)| regex.is_match(url))
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut page = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM history WHERE id IN (",
+        HISTORY_ITEM_COLUMNS
+    ));
+    let mut separated = page.separated(", ");
+    for id in &ids {
+        separated.push_bind(id);
+    }
+    page.push(") ORDER BY created_at DESC");
+    let rows = page.build().fetch_all(&pool).await?;
+    Ok(rows.iter().map(history_item_from_row).collect())
+}
+
+/// Turn free text into an FTS5 query matching rows that contain every word;
+/// words are quoted so FTS syntax characters are taken literally
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Latest sends of a saved request, oldest first
+pub async fn get_request_timeline(
+    request_id: &str,
+    since: Option<i64>,
+    limit: i64,
+) -> Result<Vec<TimelinePoint>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let rows = sqlx::query_as::<_, (String, i64, Option<i64>, Option<i64>, Option<String>)>(
+        r#"
+        SELECT id, created_at, response_status, response_time_ms, error_kind
+        FROM history
+        WHERE request_id = ? AND (? IS NULL OR created_at >= ?)
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#
+    )
+    .bind(request_id)
+    .bind(since)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .rev()
+        .map(|(history_id, created_at, status, time_ms, error_kind)| TimelinePoint {
+            history_id,
+            created_at,
+            status: status.map(|s| s as u16),
+            time_ms: time_ms.map(|t| t as u64),
+            failed: error_kind.is_some() || status.is_some_and(|s| s >= 500),
+            error_kind,
+        })
+        .collect())
+}
+
+/// History row including the stored request and response bodies
+pub async fn get_history_entry(id: &str) -> Result<Option<HistoryEntry>, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let row = sqlx::query(&format!(
+        "SELECT {}, request_config, response_body_preview, response_body_full, response_body_type,
+            content_type, content_encoding
+         FROM history WHERE id = ?",
+        HISTORY_ITEM_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&pool)
+    .await?;
+
+    Ok(row.map(|row| HistoryEntry {
+        item: history_item_from_row(&row),
+        request_config: row.get("request_config"),
+        response_body_preview: row.get("response_body_preview"),
+        response_body_full: row.get("response_body_full"),
+        response_body_type: row.get("response_body_type"),
+        content_type: row.get("content_type"),
+        content_encoding: row.get("content_encoding"),
+    }))
+}
+
+/// Stored size of a history row in bytes. `history_fts` reads its text from
+/// the row, so only the row itself is counted.
+const HISTORY_ROW_BYTES: &str = "COALESCE(LENGTH(CAST(url AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(request_headers AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(request_params AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(request_body AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(request_config AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(response_headers AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(response_body_preview AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(response_body_full AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(response_timing AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(assertion_results AS BLOB)), 0) \
+    + COALESCE(LENGTH(CAST(error_message AS BLOB)), 0)";
+
+/// Size of a history row's response body streamed to disk
+const HISTORY_FILE_BYTES: &str =
+    "CASE WHEN response_file IS NOT NULL THEN COALESCE(response_size_bytes, 0) ELSE 0 END";
+
+/// Delete history beyond the retention limits, for one workspace or all of
+/// them, with the response files of the deleted entries. The size limit
+/// keeps a workspace's newest entry even when it alone is over the limit; the
+/// age limit does not. Returns the number of deleted entries.
+pub async fn prune_history(workspace_id: Option<&str>, retention: &HistoryRetention) -> Result<u64, sqlx::Error> {
+    let pool = get_pool().await?;
+    let mut tx = pool.begin().await?;
+    let mut files: Vec<Option<String>> = Vec::new();
+
+    // An age too large to compute a cutoff for is no limit
+    let cutoff = i64::try_from(retention.max_age_days)
+        .ok()
+        .filter(|days| *days > 0)
+        .and_then(|days| days.checked_mul(86_400_000))
+        .and_then(|max_age| chrono::Utc::now().timestamp_millis().checked_sub(max_age));
+    if let Some(cutoff) = cutoff {
+        files.extend(
+            sqlx::query_scalar::<_, Option<String>>(
+                "DELETE FROM history WHERE (?1 IS NULL OR workspace_id = ?1) AND created_at < ?2
+                 RETURNING response_file"
+            )
+            .bind(workspace_id)
+            .bind(cutoff)
+            .fetch_all(&mut *tx)
+            .await?,
+        );
+    }
 
-    Ok(items)
+    if retention.max_entries > 0 || retention.max_bytes > 0 {
+        files.extend(sqlx::query_scalar::<_, Option<String>>(&format!(
+            r#"
+            DELETE FROM history WHERE id IN (
+                SELECT id FROM (
+                    SELECT id,
+                        ROW_NUMBER() OVER newest_first AS position,
+                        SUM(({}) + {}) OVER newest_first AS total_bytes
+                    FROM history
+                    WHERE (?1 IS NULL OR workspace_id = ?1)
+                    WINDOW newest_first AS (
+                        PARTITION BY workspace_id ORDER BY created_at DESC, id ROWS UNBOUNDED PRECEDING
+                    )
+                )
+                WHERE (?2 > 0 AND position > ?2) OR (?3 > 0 AND position > 1 AND total_bytes > ?3)
+            )
+            RETURNING response_file
+            "#,
+            HISTORY_ROW_BYTES,
+            HISTORY_FILE_BYTES
+        ))
+        .bind(workspace_id)
+        // Limits beyond i64 can't be reached
+        .bind(i64::try_from(retention.max_entries).unwrap_or(i64::MAX))
+        .bind(i64::try_from(retention.max_bytes).unwrap_or(i64::MAX))
+        .fetch_all(&mut *tx)
+        .await?);
+    }
+
+    tx.commit().await?;
+
+    let deleted = files.len() as u64;
+    remove_response_files(files).await;
+    Ok(deleted)
 }
 
+/// Stored history size for one workspace or all of them, plus the size of
+/// the whole database file
+pub async fn get_history_usage(workspace_id: Option<&str>) -> Result<HistoryUsage, sqlx::Error> {
+    let pool = get_pool().await?;
+
+    let (entries, row_bytes, file_bytes, oldest_at, newest_at) =
+        sqlx::query_as::<_, (i64, i64, i64, Option<i64>, Option<i64>)>(&format!(
+            "SELECT COUNT(*), COALESCE(SUM({}), 0), COALESCE(SUM({}), 0), MIN(created_at), MAX(created_at)
+             FROM history WHERE (?1 IS NULL OR workspace_id = ?1)",
+            HISTORY_ROW_BYTES,
+            HISTORY_FILE_BYTES
+        ))
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await?;
+
+    let database_bytes: i64 = sqlx::query_scalar(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()"
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(HistoryUsage {
+        entries,
+        bytes: row_bytes + file_bytes,
+        file_bytes,
+        oldest_at,
+        newest_at,
+        database_bytes,
+    })
+}
 
 pub async fn clear_history(workspace_id: &str) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
-    sqlx::query("DELETE FROM history WHERE workspace_id = ?")
+    let files = sqlx::query_scalar("DELETE FROM history WHERE workspace_id = ? RETURNING response_file")
         .bind(workspace_id)
-        .execute(&pool)
+        .fetch_all(&pool)
         .await?;
+    remove_response_files(files).await;
         
     Ok(())
 }
@@ -877,14 +2240,26 @@ pub async fn clear_history(workspace_id: &str) -> Result<(), sqlx::Error> {
 pub async fn delete_history_entry(id: &str) -> Result<(), sqlx::Error> {
     let pool = get_pool().await?;
     
-    sqlx::query("DELETE FROM history WHERE id = ?")
+    let files = sqlx::query_scalar("DELETE FROM history WHERE id = ? RETURNING response_file")
         .bind(id)
-        .execute(&pool)
+        .fetch_all(&pool)
         .await?;
+    remove_response_files(files).await;
         
     Ok(())
 }
 
+/// Delete the streamed response bodies of deleted history rows
+async fn remove_response_files(files: Vec<Option<String>>) {
+    for file in files.into_iter().flatten() {
+        if let Err(e) = tokio::fs::remove_file(&file).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to delete response file {}: {}", file, e);
+            }
+        }
+    }
+}
+
 pub async fn import_collection(data: ImportCollection) -> Result<String, sqlx::Error> {
     let pool = get_pool().await?;
     let collection_id = Uuid::new_v4().to_string();
@@ -1027,3 +2402,95 @@ pub async fn save_request_internal(
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    /// Migrated in-memory database; one connection, since each in-memory
+    /// connection is its own database
+    async fn test_pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        run_migrations(&pool).await.unwrap();
+        sqlx::query("INSERT INTO workspace (id, name, created_at, updated_at) VALUES ('ws', 'Test', 0, 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn insert_history(pool: &Pool<Sqlite>, id: &str, url: &str, body: &str) {
+        sqlx::query(
+            "INSERT INTO history (id, workspace_id, method, url, request_headers, response_body_full, response_body_type, created_at)
+             VALUES (?, 'ws', 'GET', ?, '[]', ?, 'text', 0)",
+        )
+        .bind(id)
+        .bind(url)
+        .bind(body)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn search(pool: &Pool<Sqlite>, text: &str) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT id FROM history WHERE rowid IN (SELECT rowid FROM history_fts WHERE history_fts MATCH ?) ORDER BY id",
+        )
+        .bind(fts_query(text))
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn assert_index_consistent(pool: &Pool<Sqlite>) {
+        sqlx::query("INSERT INTO history_fts (history_fts) VALUES ('integrity-check')")
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn timeline_query_uses_request_index() {
+        let pool = test_pool().await;
+        let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(
+            "EXPLAIN QUERY PLAN SELECT id FROM history WHERE request_id = 'r' AND created_at >= 0 ORDER BY created_at DESC",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert!(plan.iter().any(|(_, _, _, detail)| detail.contains("idx_history_request_created")), "{:?}", plan);
+    }
+
+    #[test]
+    fn local_scope_has_no_table() {
+        assert_eq!(scope_table(VariableScope::Environment).unwrap(), ("variable", "environment_id"));
+        assert!(scope_table(VariableScope::Local).is_err());
+    }
+
+    #[tokio::test]
+    async fn history_search_follows_inserts_updates_and_deletes() {
+        let pool = test_pool().await;
+        insert_history(&pool, "a", "https://api.example.com/users", "alice").await;
+        insert_history(&pool, "b", "https://api.example.com/orders", "bob").await;
+
+        assert_eq!(search(&pool, "users").await, vec!["a"]);
+        assert_eq!(search(&pool, "api.example.com").await, vec!["a", "b"]);
+        assert_eq!(search(&pool, "bob").await, vec!["b"]);
+
+        sqlx::query("UPDATE history SET url = 'https://api.example.com/accounts' WHERE id = 'a'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(search(&pool, "users").await.is_empty());
+        assert_eq!(search(&pool, "accounts").await, vec!["a"]);
+
+        sqlx::query("DELETE FROM history WHERE id = 'b'").execute(&pool).await.unwrap();
+        assert!(search(&pool, "bob").await.is_empty());
+        assert_index_consistent(&pool).await;
+    }
+}
@@ -0,0 +1,115 @@
+use serde_json::{json, Map, Value};
+
+/// Standard introspection query, as sent by GraphiQL
+pub const INTROSPECTION_QUERY: &str = r#"
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+    directives {
+      name
+      description
+      locations
+      args { ...InputValue }
+    }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+    isDeprecated
+    deprecationReason
+  }
+  inputFields { ...InputValue }
+  interfaces { ...TypeRef }
+  enumValues(includeDeprecated: true) {
+    name
+    description
+    isDeprecated
+    deprecationReason
+  }
+  possibleTypes { ...TypeRef }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType {
+              kind
+              name
+              ofType {
+                kind
+                name
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// Build the JSON payload for a GraphQL-over-HTTP POST.
+/// Empty variables and operation name are left out.
+pub fn request_payload(query: &str, variables: Option<&Value>, operation_name: Option<&str>) -> Value {
+    let mut payload = Map::new();
+    payload.insert("query".to_string(), json!(query));
+
+    if let Some(variables) = variables.filter(|v| !v.is_null()) {
+        payload.insert("variables".to_string(), variables.clone());
+    }
+    if let Some(operation_name) = operation_name.filter(|n| !n.is_empty()) {
+        payload.insert("operationName".to_string(), json!(operation_name));
+    }
+
+    Value::Object(payload)
+}
+
+/// Extract `data.__schema` from an introspection response
+pub fn parse_introspection(body: &[u8]) -> Result<Value, String> {
+    let mut response: Value = serde_json::from_slice(body)
+        .map_err(|e| format!("Introspection response is not JSON: {}", e))?;
+
+    match response.pointer_mut("/data/__schema").map(Value::take) {
+        Some(schema) if schema.is_object() => Ok(schema),
+        _ => {
+            let message = response
+                .pointer("/errors/0/message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("response has no data.__schema");
+            Err(format!("Introspection failed: {}", message))
+        }
+    }
+}
@@ -1,54 +1,329 @@
+use crate::auth;
+use crate::cookies::{CookieJar, SendCookieStore};
+use crate::graphql;
 use crate::models::*;
+use crate::progress::{ProgressBody, ProgressCallback, ProgressTracker};
+use crate::timing::{ConnectTimingLayer, ConnectionPhases, TimedResolver};
 use dashmap::DashMap;
 use futures::future::{AbortHandle, Abortable};
 use lazy_static::lazy_static;
-use reqwest::{redirect::Policy, Client, Method};
-use std::sync::Arc;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+        HOST, LOCATION, PROXY_AUTHORIZATION, REFERER, TRANSFER_ENCODING, WWW_AUTHENTICATE,
+    },
+    redirect::Policy, StatusCode, Certificate, Client, Identity, Method, NoProxy, Proxy, Url,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
 
 // Constants from plan
 const RESPONSE_PREVIEW_LIMIT: usize = 256 * 1024; // 256KB
+const STREAM_TO_DISK_THRESHOLD: usize = 32 * 1024 * 1024; // 32MB
+// Smaller bodies are sent in one go without upload progress
+const UPLOAD_PROGRESS_THRESHOLD: usize = 256 * 1024; // 256KB
+const CLIENT_POOL_CAPACITY: usize = 32;
+
+/// Caller-supplied state and hooks for a single send
+pub struct SendContext<'a> {
+    pub jar: &'a mut CookieJar,
+    pub download_dir: PathBuf,
+    pub on_progress: Option<ProgressCallback>,
+}
 
 lazy_static! {
     /// Global HTTP executor with thread-safe access for runtime reconfiguration
-    static ref EXECUTOR: Arc<RwLock<HttpExecutor>> = Arc::new(RwLock::new(HttpExecutor::new(10)));
+    static ref EXECUTOR: Arc<RwLock<HttpExecutor>> = Arc::new(RwLock::new(
+        HttpExecutor::new(10, None, &[]).expect("Failed to build default HTTP executor")
+    ));
     
     /// Active requests tracker for cancellation
     static ref ACTIVE_REQUESTS: DashMap<String, AbortHandle> = DashMap::new();
 }
 
-/// Quad HTTP clients for all TLS/redirect combinations
+/// Normalized transport settings; requests with equal options share a client
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TransportOptions {
+    ignore_tls: bool,
+    proxy: Option<ProxyConfig>,
+    certificate_id: Option<String>,
+}
+
+struct PooledClient {
+    client: Client,
+    last_used: u64,
+}
+
+/// LRU cache of clients keyed by transport options. Cloned clients share
+/// reqwest's connection pool, so keeping them around preserves keep-alive.
+struct ClientPool {
+    clients: HashMap<TransportOptions, PooledClient>,
+    tick: u64,
+}
+
+impl ClientPool {
+    fn new() -> Self {
+        Self {
+            clients: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Get the client for `options`, building it with `build` on a miss
+    fn get_or_build<F>(&mut self, options: &TransportOptions, build: F) -> Result<Client, HttpError>
+    where
+        F: FnOnce() -> Result<Client, HttpError>,
+    {
+        self.tick += 1;
+
+        if let Some(pooled) = self.clients.get_mut(options) {
+            pooled.last_used = self.tick;
+            return Ok(pooled.client.clone());
+        }
+
+        let client = build()?;
+
+        if self.clients.len() >= CLIENT_POOL_CAPACITY {
+            let oldest = self
+                .clients
+                .iter()
+                .min_by_key(|(_, pooled)| pooled.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                self.clients.remove(&key);
+            }
+        }
+
+        self.clients.insert(
+            options.clone(),
+            PooledClient {
+                client: client.clone(),
+                last_used: self.tick,
+            },
+        );
+
+        Ok(client)
+    }
+}
+
+/// Client identity and extra trust roots loaded from a host certificate entry
+#[derive(Default)]
+struct TlsMaterial {
+    identity: Option<Identity>,
+    ca_certs: Vec<Certificate>,
+}
+
+impl TlsMaterial {
+    /// Read the PEM/PKCS#12 identity and CA bundle referenced by `cert`
+    fn load(cert: &HostCertificate) -> Result<Self, HttpError> {
+        let read = |path: &str| {
+            std::fs::read(path).map_err(|e| HttpError::Tls {
+                message: format!("Failed to read {}: {}", path, e),
+            })
+        };
+        let tls_error = |e: reqwest::Error| HttpError::Tls {
+            message: format!("Invalid certificate for {}: {}", cert.host_pattern, e),
+        };
+
+        let identity = match cert.cert_path.as_deref().filter(|p| !p.is_empty()) {
+            Some(cert_path) if cert.cert_format == "pkcs12" => {
+                let der = read(cert_path)?;
+                let passphrase = cert.passphrase.as_deref().unwrap_or("");
+                Some(Identity::from_pkcs12_der(&der, passphrase).map_err(tls_error)?)
+            }
+            Some(cert_path) => {
+                let pem = read(cert_path)?;
+                // The key may live in the certificate file itself
+                let key = match cert.key_path.as_deref().filter(|p| !p.is_empty()) {
+                    Some(key_path) => read(key_path)?,
+                    None => pem.clone(),
+                };
+                Some(Identity::from_pkcs8_pem(&pem, &key).map_err(tls_error)?)
+            }
+            None => None,
+        };
+
+        let ca_certs = match cert.ca_path.as_deref().filter(|p| !p.is_empty()) {
+            Some(ca_path) => {
+                let bytes = read(ca_path)?;
+                match Certificate::from_pem_bundle(&bytes) {
+                    Ok(certs) if !certs.is_empty() => certs,
+                    _ => vec![Certificate::from_der(&bytes).map_err(tls_error)?],
+                }
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self { identity, ca_certs })
+    }
+}
+
+/// TLS material for the hosts matching a certificate entry. An entry whose
+/// files failed to load keeps the error, which is reported to requests for
+/// its hosts instead of failing every request.
+struct HostTls {
+    certificate_id: String,
+    host_pattern: String,
+    tls: Result<TlsMaterial, HttpError>,
+}
+
+/// HTTP executor holding global transport defaults and a pool of clients
 pub struct HttpExecutor {
-    strict_follow: Client,
-    strict_nofollow: Client,
-    insecure_follow: Client,
-    insecure_nofollow: Client,
+    pool: Mutex<ClientPool>,
+    host_tls: Vec<HostTls>,
+    max_redirects: usize,
+    proxy: Option<ProxyConfig>,
 }
 
 impl HttpExecutor {
-    /// Create new executor with specified max redirects
-    pub fn new(max_redirects: usize) -> Self {
-        Self {
-            strict_follow: build_client(false, true, max_redirects),
-            strict_nofollow: build_client(false, false, max_redirects),
-            insecure_follow: build_client(true, true, max_redirects),
-            insecure_nofollow: build_client(true, false, max_redirects),
+    /// Create new executor with specified max redirects, global proxy and host certificates
+    pub fn new(
+        max_redirects: usize,
+        proxy: Option<ProxyConfig>,
+        certificates: &[HostCertificate],
+    ) -> Result<Self, HttpError> {
+        // Validate the global proxy up front so bad settings surface immediately
+        if let Some(proxy) = proxy.as_ref().filter(|p| p.enabled) {
+            build_proxy(proxy)?;
         }
+
+        let mut host_tls = Vec::new();
+        for cert in certificates.iter().filter(|c| c.enabled) {
+            host_tls.push(HostTls {
+                certificate_id: cert.id.clone(),
+                host_pattern: cert.host_pattern.trim().to_ascii_lowercase(),
+                tls: TlsMaterial::load(cert),
+            });
+        }
+
+        // Most specific pattern wins when several match
+        host_tls.sort_by_key(|h| std::cmp::Reverse(h.host_pattern.len()));
+
+        Ok(Self {
+            pool: Mutex::new(ClientPool::new()),
+            host_tls,
+            max_redirects,
+            proxy,
+        })
+    }
+
+    /// Find the host certificate entry that applies to `url`
+    fn host_tls_for(&self, url: &str) -> Option<&HostTls> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        let port = url.port_or_known_default();
+
+        self.host_tls
+            .iter()
+            .find(|h| host_pattern_matches(&h.host_pattern, &host, port))
     }
 
-    /// Select the appropriate client based on config
-    fn select_client(&self, config: &RequestConfig) -> &Client {
-        match (config.ignore_tls, config.follow_redirects) {
-            (false, true) => &self.strict_follow,
-            (false, false) => &self.strict_nofollow,
-            (true, true) => &self.insecure_follow,
-            (true, false) => &self.insecure_nofollow,
+    /// Resolve the effective transport options for sending a request to `url`
+    fn transport_options(&self, config: &RequestConfig, url: &str) -> TransportOptions {
+        let proxy = match &config.proxy {
+            // All disabled overrides mean "direct", so share one key
+            Some(proxy) if !proxy.enabled => Some(ProxyConfig {
+                enabled: false,
+                url: String::new(),
+                username: None,
+                password: None,
+                bypass: Vec::new(),
+            }),
+            Some(proxy) => Some(proxy.clone()),
+            None => self.proxy.clone(),
+        };
+
+        TransportOptions {
+            ignore_tls: config.ignore_tls,
+            proxy,
+            certificate_id: self.host_tls_for(url).map(|h| h.certificate_id.clone()),
         }
     }
 
-    /// Execute HTTP request
-    pub async fn execute(&self, config: RequestConfig) -> Result<HttpResponse, HttpError> {
+    /// Select the client for sending `config` to `url`, which differs from
+    /// the configured URL after a redirect
+    fn select_client(&self, config: &RequestConfig, url: &str) -> Result<Client, HttpError> {
+        let options = self.transport_options(config, url);
+        let default_tls = TlsMaterial::default();
+        let tls = match self.host_tls_for(url) {
+            Some(host_tls) => host_tls.tls.as_ref().map_err(Clone::clone)?,
+            None => &default_tls,
+        };
+
+        let mut pool = self.pool.lock().unwrap_or_else(|e| e.into_inner());
+        pool.get_or_build(&options, || build_client(&options, tls))
+    }
+
+    /// Send `request` with `client`, then follow redirects one hop at a time
+    /// so each host gets its own client certificate
+    async fn send_following(
+        &self,
+        config: &RequestConfig,
+        mut client: Client,
+        mut request: reqwest::Request,
+        tracker: &Arc<ProgressTracker>,
+        phases: &Arc<Mutex<ConnectionPhases>>,
+        timeout: Option<Duration>,
+    ) -> Result<Followed, HttpError> {
+        let max_redirects = config.max_redirects.unwrap_or(self.max_redirects);
+        let mut redirects = 0;
+        let start = Instant::now();
+        let mut hop_start = start;
+        // A SigV4 signature covers the URL, so a redirect is returned rather
+        // than followed with a signature that no longer matches
+        let follow = config.follow_redirects && !matches!(config.auth, Some(AuthConfig::AwsSigV4 { .. }));
+
+        loop {
+            let method = request.method().clone();
+            let headers = request.headers().clone();
+            let sent = request.try_clone();
+            let (response, ttfb) = send_tracked(&client, request, tracker, phases, timeout).await?;
+
+            let next = if follow {
+                redirect_request(&response, &method, &headers, sent.as_ref().and_then(|r| r.try_clone()))
+            } else {
+                None
+            };
+            let Some(next) = next else {
+                return Ok(Followed {
+                    response,
+                    request: sent,
+                    ttfb,
+                    redirects: (redirects > 0).then(|| hop_start - start),
+                });
+            };
+
+            redirects += 1;
+            if redirects > max_redirects {
+                return Err(HttpError::Network {
+                    message: format!("Too many redirects (limit is {})", max_redirects),
+                });
+            }
+
+            client = self.select_client(config, next.url().as_str())?;
+            request = next;
+            hop_start = Instant::now();
+            tracker.update(|p| {
+                p.phase = RequestPhase::Connecting;
+                p.bytes_sent = 0;
+            });
+        }
+    }
+
+    /// Execute HTTP request. Cookies go through the jar scoped to the current
+    /// task by `execute_request`.
+    pub async fn execute(
+        &self,
+        config: RequestConfig,
+        send_id: &str,
+        download_dir: PathBuf,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<HttpResponse, HttpError> {
+        let tracker = Arc::new(ProgressTracker::new(send_id, on_progress));
         let start = Instant::now();
         
         // Parse method
@@ -58,7 +333,7 @@ impl HttpExecutor {
             })?;
 
         // Select appropriate client
-        let client = self.select_client(&config);
+        let client = self.select_client(&config, &config.url)?;
 
         // Build request
         let mut request = client.request(method, &config.url);
@@ -82,6 +357,15 @@ impl HttpExecutor {
                 AuthConfig::Bearer { token } => {
                     request.bearer_auth(token)
                 }
+                AuthConfig::ApiKey { key, value, placement } => match placement {
+                    ApiKeyPlacement::Header => request.header(key.as_str(), value.as_str()),
+                    ApiKeyPlacement::Query => request.query(&[(key.as_str(), value.as_str())]),
+                },
+                // Digest answers the server's challenge and SigV4 signs the
+                // built request, both below
+                AuthConfig::Digest { .. } | AuthConfig::AwsSigV4 { .. } => request,
+                // Callers exchange OAuth 2.0 settings for a bearer token first
+                AuthConfig::OAuth2(_) => request,
                 AuthConfig::None => request,
             };
         }
@@ -108,24 +392,118 @@ impl HttpExecutor {
             RequestBody::Multipart { fields } => {
                 let mut form = reqwest::multipart::Form::new();
                 for field in fields.iter().filter(|f| f.enabled) {
-                    form = form.text(field.key.clone(), field.value.clone());
+                    let part = match field.file_path.as_deref().filter(|p| !p.is_empty()) {
+                        Some(path) => {
+                            let (body, len) = open_upload_file(path).await?;
+                            let filename = field.filename.clone().unwrap_or_else(|| {
+                                Path::new(path)
+                                    .file_name()
+                                    .map(|n| n.to_string_lossy().into_owned())
+                                    .unwrap_or_default()
+                            });
+                            reqwest::multipart::Part::stream_with_length(body, len).file_name(filename)
+                        }
+                        None => reqwest::multipart::Part::text(field.value.clone()),
+                    };
+                    let part = match field.content_type.as_deref().filter(|c| !c.is_empty()) {
+                        Some(content_type) => part.mime_str(content_type).map_err(|e| HttpError::InvalidUrl {
+                            message: format!("Invalid content type for part '{}': {}", field.key, e),
+                        })?,
+                        None => part,
+                    };
+                    form = form.part(field.key.clone(), part);
                 }
                 request.multipart(form)
             }
+            RequestBody::GraphQL { query, variables, operation_name } => {
+                request.json(&graphql::request_payload(query, variables.as_ref(), operation_name.as_deref()))
+            }
+            RequestBody::File { path, content_type } => {
+                let (body, len) = open_upload_file(path).await?;
+                let body = reqwest::Body::wrap(SizedBody { inner: body, len });
+                request
+                    .header(
+                        "Content-Type",
+                        content_type.as_deref().filter(|c| !c.is_empty()).unwrap_or("application/octet-stream"),
+                    )
+                    .body(body)
+            }
         };
 
-        // Send request (cancellable)
-        let response = request.send().await.map_err(|e| {
-            if e.is_timeout() {
-                HttpError::Timeout
-            } else if e.is_connect() {
-                HttpError::Network { message: e.to_string() }
-            } else if e.is_request() {
-                HttpError::InvalidUrl { message: e.to_string() }
+        // Send request (cancellable), recording connection phases on this task
+        let phases = Arc::new(Mutex::new(ConnectionPhases::default()));
+        let idle_timeout = Duration::from_millis(config.timeout_ms);
+        // File downloads have no overall timeout, only an idle one
+        let send_timeout = config.stream_to_file.then_some(idle_timeout);
+        let mut request = request.build().map_err(|e| HttpError::InvalidUrl {
+            message: e.to_string(),
+        })?;
+
+        // SigV4 signs the final URL, headers and body
+        if let Some(AuthConfig::AwsSigV4 { access_key, secret_key, session_token, region, service }) = &config.auth {
+            let creds = auth::AwsCredentials {
+                access_key,
+                secret_key,
+                session_token: session_token.as_deref(),
+                region,
+                service,
+            };
+            auth::sign_aws_v4(&mut request, &creds, chrono::Utc::now())?;
+        }
+
+        let send_start = Instant::now();
+        let mut followed = self.send_following(&config, client, request, &tracker, &phases, send_timeout).await?;
+        let mut auth_retry = None;
+
+        // Digest answers the challenge with a copy of the request that got it.
+        // Streamed bodies can't be replayed, so their 401 is returned as-is.
+        if let (Some(AuthConfig::Digest { username, password }), Some(mut retry)) = (&config.auth, followed.request.take()) {
+            let challenge = if followed.response.status() == StatusCode::UNAUTHORIZED {
+                followed
+                    .response
+                    .headers()
+                    .get_all(WWW_AUTHENTICATE)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .find_map(auth::parse_digest_challenge)
             } else {
-                HttpError::Network { message: e.to_string() }
+                None
+            };
+
+            if let Some(challenge) = challenge {
+                let uri = match retry.url().query() {
+                    Some(query) => format!("{}?{}", retry.url().path(), query),
+                    None => retry.url().path().to_string(),
+                };
+                let body = retry.body().and_then(|b| b.as_bytes()).unwrap_or_default();
+                let authorization = auth::digest_authorization(
+                    &challenge,
+                    username,
+                    password,
+                    retry.method().as_str(),
+                    &uri,
+                    body,
+                )?;
+                let authorization = HeaderValue::from_str(&authorization).map_err(|e| HttpError::InvalidUrl {
+                    message: e.to_string(),
+                })?;
+                retry.headers_mut().insert(AUTHORIZATION, authorization);
+
+                tracker.update(|p| {
+                    p.phase = RequestPhase::Connecting;
+                    p.bytes_sent = 0;
+                });
+                let client = self.select_client(&config, retry.url().as_str())?;
+                auth_retry = Some(send_start.elapsed());
+                followed = self.send_following(&config, client, retry, &tracker, &phases, send_timeout).await?;
             }
-        })?;
+        }
+        let Followed { mut response, ttfb, redirects, .. } = followed;
+        let total_receive = response.content_length();
+        tracker.update(|p| {
+            p.phase = RequestPhase::Receiving;
+            p.total_receive = total_receive;
+        });
 
         // Extract response metadata
         let status = response.status().as_u16();
@@ -156,14 +534,64 @@ impl HttpExecutor {
             })
             .collect();
 
-        // Read body bytes (cancellable)
-        let bytes = response.bytes().await.map_err(|e| HttpError::Network { 
-            message: e.to_string() 
-        })?;
+        // Read body chunks (cancellable), spilling to disk for file mode or large bodies
+        let download_start = Instant::now();
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut file: Option<ResponseFile> = None;
+        let mut received: u64 = 0;
+
+        loop {
+            let chunk = if config.stream_to_file {
+                tokio::time::timeout(idle_timeout, response.chunk())
+                    .await
+                    .map_err(|_| HttpError::Timeout)?
+            } else {
+                response.chunk().await
+            };
+            let chunk = match chunk.map_err(|e| HttpError::Network { message: e.to_string() })? {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            received += chunk.len() as u64;
+
+            match file.as_mut() {
+                Some(file) => file.write(&chunk).await?,
+                None => {
+                    bytes.extend_from_slice(&chunk);
+                    if config.stream_to_file || bytes.len() > STREAM_TO_DISK_THRESHOLD {
+                        let mut created = ResponseFile::create(&download_dir, content_type.as_deref()).await?;
+                        created.write(&bytes).await?;
+                        // Keep only what the preview needs in memory
+                        bytes.truncate(RESPONSE_PREVIEW_LIMIT);
+                        bytes.shrink_to_fit();
+                        file = Some(created);
+                    }
+                }
+            }
+
+            tracker.update(|p| p.bytes_received = received);
+        }
 
-        let size_bytes = bytes.len();
+        tracker.update(|p| {
+            p.phase = RequestPhase::Complete;
+            p.bytes_received = received;
+        });
+
+        let size_bytes = received as usize;
         let time_ms = start.elapsed().as_millis();
 
+        let phases = *phases.lock().unwrap_or_else(|e| e.into_inner());
+        let timing = TimingBreakdown {
+            dns_ms: phases.dns.map(|d| d.as_millis() as u64),
+            connect_ms: phases.connect.map(|c| c.as_millis() as u64),
+            tls_ms: phases.tls.map(|t| t.as_millis() as u64),
+            redirect_ms: redirects.map(|r| r.as_millis() as u64),
+            auth_retry_ms: auth_retry.map(|a| a.as_millis() as u64),
+            ttfb_ms: ttfb.as_millis() as u64,
+            download_ms: download_start.elapsed().as_millis() as u64,
+            total_ms: time_ms as u64,
+        };
+
         // Determine if content is text-based
         let is_text = content_type
             .as_ref()
@@ -176,7 +604,22 @@ impl HttpExecutor {
             .unwrap_or(false);
 
         // Parse body based on type and size
-        let body = if is_text {
+        let body = if let Some(file) = file {
+            let preview = if is_text {
+                String::from_utf8_lossy(&bytes[..bytes.len().min(RESPONSE_PREVIEW_LIMIT)]).into_owned()
+            } else {
+                bytes
+                    .iter()
+                    .take(256)
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>()
+            };
+            ResponseBody::File {
+                path: file.finish().await?,
+                size: size_bytes,
+                preview,
+            }
+        } else if is_text {
             match String::from_utf8(bytes.to_vec()) {
                 Ok(text) => {
                     if text.len() > RESPONSE_PREVIEW_LIMIT {
@@ -215,77 +658,656 @@ impl HttpExecutor {
             body,
             size_bytes,
             time_ms,
+            timing,
             content_type,
             content_encoding,
         })
     }
 }
 
-/// Build reqwest client with specified configuration
-fn build_client(insecure: bool, follow: bool, max_redirects: usize) -> Client {
-    let policy = if follow {
-        Policy::limited(max_redirects)
+/// Final response of a send and how long it took to reach it
+struct Followed {
+    response: reqwest::Response,
+    request: Option<reqwest::Request>,  // Copy of the request that got `response`, if the body could be copied
+    ttfb: Duration,                     // Final request sent until its response headers arrived
+    redirects: Option<Duration>,        // Hops before the final one, when redirects were followed
+}
+
+/// Send a built request, reporting upload progress for streamed or large
+/// bodies and recording connection phases on this task. Returns the response
+/// with its time to first byte.
+async fn send_tracked(
+    client: &Client,
+    mut request: reqwest::Request,
+    tracker: &Arc<ProgressTracker>,
+    phases: &Arc<Mutex<ConnectionPhases>>,
+    timeout: Option<Duration>,
+) -> Result<(reqwest::Response, Duration), HttpError> {
+    let track_upload = request
+        .body()
+        .map(|b| b.as_bytes().map(|bytes| bytes.len() >= UPLOAD_PROGRESS_THRESHOLD).unwrap_or(true))
+        .unwrap_or(false);
+    if track_upload {
+        if let Some(body) = request.body_mut().take() {
+            *request.body_mut() = Some(reqwest::Body::wrap(ProgressBody::new(body, tracker.clone())));
+        }
     } else {
-        Policy::none()
+        let total = request.body().and_then(|b| b.as_bytes()).map(|b| b.len() as u64);
+        tracker.update(|p| {
+            p.phase = RequestPhase::Sending;
+            p.total_send = total;
+            p.bytes_sent = total.unwrap_or(0);
+        });
+    }
+
+    tracker.take_body_sent_at();
+    let setup_before = phases.lock().unwrap_or_else(|e| e.into_inner()).setup();
+    let start = Instant::now();
+
+    let sending = ConnectionPhases::record(phases.clone(), client.execute(request));
+    let sent = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, sending)
+            .await
+            .map_err(|_| HttpError::Timeout)?,
+        None => sending.await,
+    };
+    let headers_at = Instant::now();
+
+    // The request goes out once its connection is set up and, for streamed
+    // bodies, once the body is written
+    let setup = phases.lock().unwrap_or_else(|e| e.into_inner()).setup().saturating_sub(setup_before);
+    let ready = start + setup;
+    let sent_at = tracker.take_body_sent_at().map_or(ready, |at| at.max(ready));
+    let ttfb = headers_at.saturating_duration_since(sent_at);
+
+    let response = sent.map_err(|e| {
+        if e.is_timeout() {
+            HttpError::Timeout
+        } else if e.is_connect() {
+            HttpError::Network { message: e.to_string() }
+        } else if e.is_request() {
+            HttpError::InvalidUrl { message: e.to_string() }
+        } else {
+            HttpError::Network { message: e.to_string() }
+        }
+    })?;
+    Ok((response, ttfb))
+}
+
+/// Open a file to be streamed as (part of) a request body
+async fn open_upload_file(path: &str) -> Result<(reqwest::Body, u64), HttpError> {
+    let io_error = |e: std::io::Error| HttpError::Io {
+        message: format!("Failed to read upload file {}: {}", path, e),
+    };
+
+    let file = tokio::fs::File::open(path).await.map_err(io_error)?;
+    let len = file.metadata().await.map_err(io_error)?.len();
+
+    Ok((reqwest::Body::from(file), len))
+}
+
+/// Streaming body with a known length, so Content-Length and upload
+/// progress totals are set for file uploads
+struct SizedBody {
+    inner: reqwest::Body,
+    len: u64,
+}
+
+impl http_body::Body for SizedBody {
+    type Data = bytes::Bytes;
+    type Error = reqwest::Error;
+
+    fn poll_frame(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        std::pin::Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        http_body::SizeHint::with_exact(self.len)
+    }
+}
+
+/// Response body being streamed to disk. The partial file is removed if the
+/// download fails or is cancelled before `finish` is called.
+struct ResponseFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+    finished: bool,
+}
+
+impl ResponseFile {
+    /// Create a uniquely named file whose extension matches the content type,
+    /// so the system can pick an application to open it with
+    async fn create(dir: &Path, content_type: Option<&str>) -> Result<Self, HttpError> {
+        let io_error = |e: std::io::Error| HttpError::Io {
+            message: format!("Failed to write response file: {}", e),
+        };
+
+        tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+        let path = dir.join(format!("{}.{}", uuid::Uuid::new_v4(), file_extension(content_type)));
+        let file = tokio::fs::File::create(&path).await.map_err(io_error)?;
+
+        Ok(Self {
+            path,
+            file,
+            finished: false,
+        })
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), HttpError> {
+        self.file.write_all(data).await.map_err(|e| HttpError::Io {
+            message: format!("Failed to write response file: {}", e),
+        })
+    }
+
+    /// Flush the file and keep it on disk, returning its path
+    async fn finish(mut self) -> Result<String, HttpError> {
+        self.file.flush().await.map_err(|e| HttpError::Io {
+            message: format!("Failed to write response file: {}", e),
+        })?;
+        self.finished = true;
+        Ok(self.path.display().to_string())
+    }
+}
+
+impl Drop for ResponseFile {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// File extension for a response body of the given content type
+fn file_extension(content_type: Option<&str>) -> &'static str {
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match essence.as_str() {
+        "application/json" | "text/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "text/html" => "html",
+        "text/plain" => "txt",
+        "text/csv" => "csv",
+        "text/css" => "css",
+        "application/javascript" | "text/javascript" => "js",
+        "application/pdf" => "pdf",
+        "application/zip" => "zip",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "application/x-tar" => "tar",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "audio/mpeg" => "mp3",
+        "video/mp4" => "mp4",
+        other if other.ends_with("+json") => "json",
+        other if other.ends_with("+xml") => "xml",
+        _ => "bin",
+    }
+}
+
+/// Build reqwest client with specified configuration
+fn build_client(options: &TransportOptions, tls: &TlsMaterial) -> Result<Client, HttpError> {
+    // Redirects are followed by `send_following`, which picks the client for each hop
+    let mut builder = Client::builder()
+        .danger_accept_invalid_certs(options.ignore_tls)
+        .redirect(Policy::none())
+        .dns_resolver(Arc::new(TimedResolver))
+        .connector_layer(ConnectTimingLayer)
+        .cookie_provider(Arc::new(SendCookieStore));
+
+    if let Some(identity) = &tls.identity {
+        builder = builder.identity(identity.clone());
+    }
+    for cert in &tls.ca_certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+
+    match &options.proxy {
+        Some(proxy) if proxy.enabled => {
+            builder = builder.proxy(build_proxy(proxy)?);
+        }
+        // An explicitly disabled proxy means a direct connection, ignoring env vars
+        Some(_) => {
+            builder = builder.no_proxy();
+        }
+        None => {}
+    }
+
+    builder.build().map_err(|e| HttpError::Network {
+        message: format!("Failed to build HTTP client: {}", e),
+    })
+}
+
+/// Build the request a redirect response points to. 301/302/303 become a
+/// body-less GET (HEAD stays HEAD); 307/308 repeat `replayable`, so they're
+/// not followed when the body couldn't be copied. Credentials are dropped
+/// when the redirect leaves the host.
+fn redirect_request(
+    response: &reqwest::Response,
+    method: &Method,
+    headers: &HeaderMap,
+    replayable: Option<reqwest::Request>,
+) -> Option<reqwest::Request> {
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    let previous = response.url();
+    let next = previous.join(location).ok()?;
+    if !matches!(next.scheme(), "http" | "https") {
+        return None;
+    }
+
+    let mut request = match response.status() {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
+            let method = match *method {
+                Method::GET | Method::HEAD => method.clone(),
+                _ => Method::GET,
+            };
+            let mut request = reqwest::Request::new(method, next.clone());
+            *request.headers_mut() = headers.clone();
+            for name in [TRANSFER_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, CONTENT_LENGTH] {
+                request.headers_mut().remove(name);
+            }
+            request
+        }
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+            let mut request = replayable?;
+            *request.url_mut() = next.clone();
+            request
+        }
+        _ => return None,
     };
 
-    Client::builder()
-        .danger_accept_invalid_certs(insecure)
-        .redirect(policy)
-        .build()
-        .expect("Failed to build HTTP client")
+    let headers = request.headers_mut();
+    // Credentials, a Host set for the previous host and the x-amz-* signing
+    // headers stay with the host they were meant for
+    if next.host_str() != previous.host_str() || next.port_or_known_default() != previous.port_or_known_default() {
+        for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, WWW_AUTHENTICATE, HOST] {
+            headers.remove(name);
+        }
+        let amz: Vec<HeaderName> = headers.keys().filter(|n| n.as_str().starts_with("x-amz-")).cloned().collect();
+        for name in amz {
+            headers.remove(name);
+        }
+    }
+
+    // Like browsers, send the previous URL as Referer unless leaving https
+    headers.remove(REFERER);
+    if previous.scheme() == next.scheme() || next.scheme() == "https" {
+        let mut referer = previous.clone();
+        referer.set_fragment(None);
+        let _ = referer.set_username("");
+        let _ = referer.set_password(None);
+        if let Ok(value) = HeaderValue::from_str(referer.as_str()) {
+            headers.insert(REFERER, value);
+        }
+    }
+
+    Some(request)
+}
+
+/// Convert proxy settings into a reqwest proxy
+fn build_proxy(config: &ProxyConfig) -> Result<Proxy, HttpError> {
+    let mut proxy = Proxy::all(config.url.trim()).map_err(|e| HttpError::InvalidUrl {
+        message: format!("Invalid proxy URL: {}", e),
+    })?;
+
+    if let Some(username) = config.username.as_deref().filter(|u| !u.is_empty()) {
+        proxy = proxy.basic_auth(username, config.password.as_deref().unwrap_or(""));
+    }
+
+    let bypass = config
+        .bypass
+        .iter()
+        .map(|h| h.trim())
+        .filter(|h| !h.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+    proxy = proxy.no_proxy(NoProxy::from_string(&bypass));
+
+    Ok(proxy)
+}
+
+/// Match a host against a certificate pattern such as `api.example.com`,
+/// `*.example.com` or `*.example.com:8443`
+fn host_pattern_matches(pattern: &str, host: &str, port: Option<u16>) -> bool {
+    let (host_pattern, port_pattern) = match pattern.rsplit_once(':') {
+        Some((h, p)) if p.parse::<u16>().is_ok() => (h, p.parse::<u16>().ok()),
+        _ => (pattern, None),
+    };
+
+    if port_pattern.is_some() && port_pattern != port {
+        return false;
+    }
+
+    match host_pattern.strip_prefix("*.") {
+        Some(suffix) => {
+            host.len() > suffix.len()
+                && host.ends_with(suffix)
+                && host[..host.len() - suffix.len()].ends_with('.')
+        }
+        None => host == host_pattern,
+    }
 }
 
 /// Rebuild executor when settings change
-pub async fn rebuild_executor(max_redirects: usize) {
+pub async fn rebuild_executor(
+    max_redirects: usize,
+    proxy: Option<ProxyConfig>,
+    certificates: &[HostCertificate],
+) -> Result<(), HttpError> {
+    let rebuilt = HttpExecutor::new(max_redirects, proxy, certificates)?;
     let mut executor = EXECUTOR.write().await;
-    *executor = HttpExecutor::new(max_redirects);
+    *executor = rebuilt;
+    Ok(())
+}
+
+/// Load error of the certificate entry `certificate_id`, if its files couldn't be used
+pub async fn certificate_error(certificate_id: &str) -> Option<HttpError> {
+    let executor = EXECUTOR.read().await;
+    executor
+        .host_tls
+        .iter()
+        .find(|h| h.certificate_id == certificate_id)
+        .and_then(|h| h.tls.as_ref().err().cloned())
 }
 
-/// Execute request with timeout and cancellation support
-pub async fn execute_request(config: RequestConfig) -> SendResult {
-    let request_id = uuid::Uuid::new_v4().to_string();
+/// Execute request with timeout and cancellation support. The caller may
+/// supply `send_id` up front so it can cancel or follow progress.
+pub async fn execute_request(
+    config: RequestConfig,
+    send_id: Option<String>,
+    ctx: SendContext<'_>,
+) -> SendResult {
+    let send_id = send_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     let (abort_handle, abort_reg) = AbortHandle::new_pair();
 
-    ACTIVE_REQUESTS.insert(request_id.clone(), abort_handle);
+    ACTIVE_REQUESTS.insert(send_id.clone(), abort_handle);
 
     let executor = EXECUTOR.read().await;
-    let timeout_duration = Duration::from_millis(config.timeout_ms);
+    // File downloads may legitimately run long, so `execute` applies an idle timeout instead
+    let timeout_duration = if config.stream_to_file {
+        Duration::MAX
+    } else {
+        Duration::from_millis(config.timeout_ms)
+    };
 
-    let result = tokio::time::timeout(
-        timeout_duration,
-        Abortable::new(executor.execute(config), abort_reg),
-    )
-    .await;
+    // Every hop, redirects included, reads and stores cookies through the jar
+    let SendContext { jar, download_dir, on_progress } = ctx;
+    let result = jar
+        .scope(tokio::time::timeout(
+            timeout_duration,
+            Abortable::new(executor.execute(config, &send_id, download_dir, on_progress), abort_reg),
+        ))
+        .await;
 
-    ACTIVE_REQUESTS.remove(&request_id);
+    ACTIVE_REQUESTS.remove(&send_id);
 
     match result {
         Ok(Ok(Ok(response))) => SendResult::Success {
-            request_id,
-            response,
+            send_id,
+            response: Box::new(response),
+            scripts: Vec::new(),
+            assertions: Vec::new(),
         },
         Ok(Ok(Err(error))) => SendResult::Failed {
-            request_id,
+            send_id,
             error,
+            scripts: Vec::new(),
         },
         Ok(Err(_)) => SendResult::Failed {
-            request_id,
+            send_id,
             error: HttpError::Cancelled,
+            scripts: Vec::new(),
         },
         Err(_) => SendResult::Failed {
-            request_id,
+            send_id,
             error: HttpError::Timeout,
+            scripts: Vec::new(),
         },
     }
 }
 
 /// Cancel an active request
-pub fn cancel_request(request_id: &str) -> bool {
-    if let Some((_, handle)) = ACTIVE_REQUESTS.remove(request_id) {
+pub fn cancel_request(send_id: &str) -> bool {
+    if let Some((_, handle)) = ACTIVE_REQUESTS.remove(send_id) {
         handle.abort();
         true
     } else {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn config(url: String) -> RequestConfig {
+        RequestConfig {
+            url,
+            timeout_ms: 5_000,
+            // Bypass any proxy configured in the environment
+            proxy: Some(ProxyConfig {
+                enabled: false,
+                url: String::new(),
+                username: None,
+                password: None,
+                bypass: Vec::new(),
+            }),
+            ..RequestConfig::editor_defaults()
+        }
+    }
+
+    fn broken_certificate(host_pattern: &str) -> HostCertificate {
+        HostCertificate {
+            id: "broken".to_string(),
+            host_pattern: host_pattern.to_string(),
+            cert_format: "pem".to_string(),
+            cert_path: Some("/nonexistent/client.pem".to_string()),
+            key_path: None,
+            passphrase: None,
+            ca_path: None,
+            enabled: true,
+            created_at: 0,
+        }
+    }
+
+    /// Server answering `/start` with a redirect of the given status to
+    /// `/final`, and everything else with 200. Records each request, lowercased.
+    async fn mock_redirect_server(status: &'static str, location: fn(u16) -> String) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                    if let Some(head_end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .and_then(|l| l.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= head_end + 4 + length || n == 0 {
+                            break;
+                        }
+                    }
+                }
+                let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+
+                let response = if text.contains(" /start ") {
+                    format!(
+                        "HTTP/1.1 {}\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status,
+                        location(port)
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndone".to_string()
+                };
+                recorded.lock().unwrap().push(text);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (format!("http://127.0.0.1:{}", port), requests)
+    }
+
+    async fn execute(executor: &HttpExecutor, config: RequestConfig) -> Result<HttpResponse, HttpError> {
+        executor.execute(config, "test", std::env::temp_dir(), None).await
+    }
+
+    #[test]
+    fn host_patterns_match_wildcards_and_ports() {
+        assert!(host_pattern_matches("api.example.com", "api.example.com", Some(443)));
+        assert!(host_pattern_matches("*.example.com", "api.example.com", Some(443)));
+        assert!(!host_pattern_matches("*.example.com", "example.com", Some(443)));
+        assert!(!host_pattern_matches("*.example.com", "badexample.com", Some(443)));
+        assert!(host_pattern_matches("api.example.com:8443", "api.example.com", Some(8443)));
+        assert!(!host_pattern_matches("api.example.com:8443", "api.example.com", Some(443)));
+    }
+
+    #[test]
+    fn broken_certificate_only_fails_its_hosts() {
+        let executor = HttpExecutor::new(10, None, &[broken_certificate("broken.example.com")]).unwrap();
+        let request = config(String::new());
+
+        assert!(matches!(
+            executor.select_client(&request, "https://broken.example.com/"),
+            Err(HttpError::Tls { .. })
+        ));
+        assert!(executor.select_client(&request, "https://other.example.com/").is_ok());
+    }
+
+    #[tokio::test]
+    async fn redirect_uses_certificate_of_target_host() {
+        let (base, requests) = mock_redirect_server("302 Found", |port| format!("http://localhost:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[broken_certificate("localhost")]).unwrap();
+
+        let result = execute(&executor, config(format!("{}/start", base))).await;
+        assert!(matches!(result, Err(HttpError::Tls { .. })));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        let not_followed = RequestConfig { follow_redirects: false, ..config(format!("{}/start", base)) };
+        assert_eq!(execute(&executor, not_followed).await.unwrap().status, 302);
+    }
+
+    #[tokio::test]
+    async fn see_other_turns_into_get_and_keeps_same_host_credentials() {
+        let (base, requests) = mock_redirect_server("303 See Other", |port| format!("http://127.0.0.1:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[]).unwrap();
+
+        let mut request = config(format!("{}/start", base));
+        request.method = "POST".to_string();
+        request.auth = Some(AuthConfig::Bearer { token: "t0ken".to_string() });
+        request.body = RequestBody::Raw { mime: "text/plain".to_string(), content: "payload".to_string() };
+
+        let response = execute(&executor, request).await.unwrap();
+        assert_eq!(response.status, 200);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("get /final "));
+        assert!(requests[1].contains("authorization: bearer t0ken"));
+        assert!(requests[1].contains(&format!("referer: {}/start", base)));
+        assert!(!requests[1].contains("content-type"));
+        assert!(!requests[1].contains("payload"));
+    }
+
+    #[tokio::test]
+    async fn temporary_redirect_repeats_body_and_drops_credentials_across_hosts() {
+        let (base, requests) = mock_redirect_server("307 Temporary Redirect", |port| format!("http://localhost:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[]).unwrap();
+        let header = |key: &str, value: &str| KeyValue { key: key.to_string(), value: value.to_string(), enabled: true };
+
+        let mut request = config(format!("{}/start", base));
+        request.method = "PUT".to_string();
+        request.auth = Some(AuthConfig::Bearer { token: "t0ken".to_string() });
+        request.headers = vec![
+            header("Host", &base["http://".len()..]),
+            header("X-Amz-Security-Token", "session"),
+        ];
+        request.body = RequestBody::Raw { mime: "text/plain".to_string(), content: "payload".to_string() };
+
+        // localhost may resolve to ::1 first, where nothing listens; the
+        // connector falls back to 127.0.0.1
+        let response = execute(&executor, request).await.unwrap();
+        assert_eq!(response.status, 200);
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[1].starts_with("put /final "));
+        assert!(requests[1].ends_with("payload"));
+        assert!(!requests[1].contains("authorization"));
+        assert!(requests[0].contains("x-amz-security-token"));
+        assert!(!requests[1].contains("x-amz-security-token"));
+        assert!(requests[1].contains("host: localhost:"), "{}", requests[1]);
+    }
+
+    #[tokio::test]
+    async fn signed_request_redirect_is_returned_not_followed() {
+        let (base, requests) = mock_redirect_server("307 Temporary Redirect", |port| format!("http://localhost:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[]).unwrap();
+
+        let mut request = config(format!("{}/start", base));
+        request.auth = Some(AuthConfig::AwsSigV4 {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: Some("session".to_string()),
+            region: "us-east-1".to_string(),
+            service: "s3".to_string(),
+        });
+
+        let response = execute(&executor, request).await.unwrap();
+        assert_eq!(response.status, 307);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn plain_connection_has_tcp_but_no_tls_phase() {
+        let (base, _) = mock_redirect_server("302 Found", |port| format!("http://127.0.0.1:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[]).unwrap();
+
+        let response = execute(&executor, config(format!("{}/final", base))).await.unwrap();
+        assert_eq!(response.timing.dns_ms, None);
+        assert!(response.timing.connect_ms.is_some());
+        assert_eq!(response.timing.tls_ms, None);
+    }
+
+    #[tokio::test]
+    async fn redirect_hops_are_timed_apart_from_ttfb() {
+        let (base, _) = mock_redirect_server("302 Found", |port| format!("http://127.0.0.1:{}/final", port)).await;
+        let executor = HttpExecutor::new(10, None, &[]).unwrap();
+
+        let direct = execute(&executor, config(format!("{}/final", base))).await.unwrap();
+        assert_eq!(direct.timing.redirect_ms, None);
+        assert_eq!(direct.timing.auth_retry_ms, None);
+
+        let redirected = execute(&executor, config(format!("{}/start", base))).await.unwrap();
+        let timing = redirected.timing;
+        let redirect_ms = timing.redirect_ms.expect("the redirect should be timed");
+        assert_eq!(timing.auth_retry_ms, None);
+        assert!(redirect_ms + timing.ttfb_ms + timing.download_ms <= timing.total_ms, "{:?}", timing);
+    }
+
+    #[tokio::test]
+    async fn redirect_limit_is_enforced() {
+        let (base, _) = mock_redirect_server("302 Found", |port| format!("http://127.0.0.1:{}/start", port)).await;
+        let executor = HttpExecutor::new(3, None, &[]).unwrap();
+
+        let result = execute(&executor, config(format!("{}/start", base))).await;
+        assert!(matches!(result, Err(HttpError::Network { message }) if message.contains("limit is 3")));
+    }
+}
@@ -5,6 +5,22 @@ mod http;
 mod db;
 mod models;
 mod commands;
+mod cookies;
+mod auth;
+mod oauth;
+mod redact;
+mod secrets;
+mod template;
+mod scripting;
+mod assertions;
+mod runner;
+mod replay;
+mod trend;
+mod graphql;
+mod progress;
+mod timing;
+
+use tauri::Manager;
 
 
 
@@ -17,15 +33,38 @@ fn main() {
             tauri::async_runtime::spawn(async move {
                 if let Err(e) = db::init_db(&app_handle).await {
                     eprintln!("Failed to initialize database: {}", e);
+                    return;
+                }
+                // Load the key for secret variables
+                match app_handle.path().app_data_dir() {
+                    Ok(dir) => {
+                        if let Err(e) = secrets::init(dir.join("secret.key")).await {
+                            eprintln!("Failed to load secrets key: {}", e);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to resolve app data dir: {}", e),
+                }
+                // Apply persisted transport settings (redirects, proxy)
+                if let Err(e) = commands::reload_executor().await {
+                    eprintln!("{}", e);
                 }
+                // Drop history that aged out while the app was closed
+                commands::prune_history(None).await;
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::send_request,
             commands::cancel_request,
+            commands::run_collection,
+            commands::cancel_run,
+            commands::save_response_file,
+            commands::open_response_file,
             commands::get_settings,
             commands::update_setting,
+            commands::get_host_certificates,
+            commands::save_host_certificate,
+            commands::delete_host_certificate,
             commands::create_collection,
             commands::create_folder,
             commands::save_request,
@@ -47,10 +86,31 @@ fn main() {
             commands::get_history,
             commands::clear_history,
             commands::delete_history_entry,
+            commands::query_history,
+            commands::get_history_entry,
+            commands::get_request_timeline,
+            commands::replay_history_entry,
+            commands::get_history_usage,
+            commands::introspect_graphql,
+            commands::get_graphql_schema,
+            commands::get_cookies,
+            commands::save_cookie,
+            commands::delete_cookie,
+            commands::clear_cookies,
+            commands::get_oauth2_token,
+            commands::clear_oauth2_token,
+            commands::clear_oauth2_tokens,
             commands::get_active_variables,
+            commands::get_resolved_variables,
+            commands::get_scope_variables,
+            commands::save_scope_variables,
+            commands::delete_scope_variable,
             commands::import_collection,
             commands::update_request,
             commands::clear_all_data,
+            commands::get_secrets_status,
+            commands::unlock_secrets,
+            commands::set_secrets_passphrase,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -8,6 +8,21 @@ pub struct KeyValue {
     pub enabled: bool,
 }
 
+/// Multipart form field; a text value, or a file when `file_path` is set.
+/// Serialized compatibly with `KeyValue` so older saved bodies still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartField {
+    pub key: String,
+    pub value: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub file_path: Option<String>,
+    #[serde(default)]
+    pub filename: Option<String>,  // Defaults to the file's name
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
 /// Request body types with tagged enum to prevent invalid states
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -24,7 +39,18 @@ pub enum RequestBody {
         fields: Vec<KeyValue> 
     },
     Multipart { 
-        fields: Vec<KeyValue> 
+        fields: Vec<MultipartField> 
+    },
+    File {
+        path: String,
+        content_type: Option<String>,
+    },
+    GraphQL {
+        query: String,
+        #[serde(default)]
+        variables: Option<serde_json::Value>,
+        #[serde(default)]
+        operation_name: Option<String>,
     },
 }
 
@@ -40,6 +66,139 @@ pub enum AuthConfig {
     Bearer { 
         token: String 
     },
+    ApiKey {
+        key: String,
+        value: String,
+        #[serde(default)]
+        placement: ApiKeyPlacement,
+    },
+    Digest {
+        username: String,
+        password: String,
+    },
+    AwsSigV4 {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+        region: String,
+        service: String,
+    },
+    OAuth2(OAuth2Config),
+}
+
+/// OAuth 2.0 grant used to obtain an access token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2Grant {
+    ClientCredentials,
+    Password,
+    RefreshToken,
+    AuthorizationCode,  // With PKCE and a loopback redirect
+}
+
+/// OAuth 2.0 settings; the acquired token is sent as a bearer token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Config {
+    pub grant_type: OAuth2Grant,
+    pub token_url: String,
+    #[serde(default)]
+    pub auth_url: Option<String>,  // Authorization code only
+    #[serde(default)]
+    pub redirect_uri: Option<String>,  // Loopback URI, defaults to a free port on 127.0.0.1
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,  // Password grant only
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,  // Refresh token grant only
+    #[serde(default)]
+    pub credentials_in_body: bool,  // Send client credentials as form fields instead of Basic auth
+}
+
+/// Access token cached per workspace and OAuth 2.0 configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuth2Token {
+    pub workspace_id: String,
+    pub cache_key: String,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    pub scope: Option<String>,
+    pub expires_at: Option<i64>,  // Unix millis, None if the server gave no lifetime
+    pub created_at: i64,
+}
+
+/// Where an API key is sent
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyPlacement {
+    #[default]
+    Header,
+    Query,
+}
+
+/// Proxy settings, either global (from `settings`) or per-request
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    pub enabled: bool,
+    pub url: String,  // http://, https:// or socks5:// scheme
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub bypass: Vec<String>,  // NO_PROXY style host list
+}
+
+/// Level a variable is defined at, from most to least specific
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableScope {
+    Local,  // Set for a single send, never stored
+    Folder,
+    Collection,
+    Environment,
+    Global,
+}
+
+/// Variable visible to a request, with where its value came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedVariable {
+    pub key: String,
+    pub value: String,  // Masked for secrets when sent to the frontend
+    pub is_secret: bool,
+    pub scope: VariableScope,
+    pub source_id: String,  // Folder, collection, environment or workspace id
+}
+
+/// Where a request lives, for resolving collection and folder variables
+#[derive(Debug, Clone, Default)]
+pub struct VariableLocation {
+    pub collection_id: Option<String>,
+    pub folder_ids: Vec<String>,  // Innermost first
+}
+
+/// How secret variables are keyed and whether they can be decrypted now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretStatus {
+    pub key_source: String,  // "keyfile" or "passphrase"
+    pub unlocked: bool,
+}
+
+/// Client identity and extra CA certificates applied to matching hosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostCertificate {
+    pub id: String,
+    pub host_pattern: String,  // e.g. api.internal.com, *.internal.com:8443
+    pub cert_format: String,   // "pem" or "pkcs12"
+    pub cert_path: Option<String>,
+    pub key_path: Option<String>,  // PEM only, defaults to cert_path
+    pub passphrase: Option<String>,  // PKCS#12 only
+    pub ca_path: Option<String>,
+    pub enabled: bool,
+    pub created_at: i64,
 }
 
 /// Request configuration sent from frontend
@@ -54,6 +213,85 @@ pub struct RequestConfig {
     pub timeout_ms: u64,
     pub ignore_tls: bool,
     pub follow_redirects: bool,
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,  // Overrides the global proxy when set
+    #[serde(default)]
+    pub max_redirects: Option<usize>,  // Overrides the global max_redirects when set
+    #[serde(default)]
+    pub stream_to_file: bool,  // Stream the body to disk instead of memory
+    #[serde(default)]
+    pub scripts: RequestScripts,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+}
+
+impl RequestConfig {
+    /// A new request as the request editor starts it: GET with no URL, a
+    /// 30s timeout and redirects followed
+    pub fn editor_defaults() -> Self {
+        Self {
+            method: "GET".to_string(),
+            url: String::new(),
+            headers: Vec::new(),
+            params: Vec::new(),
+            body: RequestBody::None,
+            auth: None,
+            timeout_ms: 30_000,
+            ignore_tls: false,
+            follow_redirects: true,
+            proxy: None,
+            max_redirects: None,
+            stream_to_file: false,
+            scripts: RequestScripts::default(),
+            assertions: Vec::new(),
+        }
+    }
+}
+
+/// Check evaluated against every response of a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Assertion {
+    StatusEquals { status: u16 },
+    StatusInRange { min: u16, max: u16 },  // Inclusive
+    HeaderExists { name: String },
+    HeaderMatches { name: String, pattern: String },  // Regex
+    JsonPathEquals { path: String, value: serde_json::Value },  // e.g. $.items[0].id
+    BodyContains { text: String },
+    ResponseTimeBelow { max_ms: u64 },
+    JsonSchema { schema: serde_json::Value },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    pub message: String,
+}
+
+/// Rhai scripts run before sending and after receiving the response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestScripts {
+    #[serde(default)]
+    pub pre_request: String,
+    #[serde(default)]
+    pub post_response: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptStage {
+    PreRequest,
+    PostResponse,
+}
+
+/// Outcome of a script run, returned with the response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptReport {
+    pub stage: ScriptStage,
+    pub logs: Vec<String>,
+    pub variables_set: Vec<String>,  // Names only, values may be secret
+    pub error: Option<String>,
 }
 
 /// Response body with different variants for safe handling
@@ -70,6 +308,34 @@ pub enum ResponseBody {
         content: String, 
         original_size: usize 
     },
+    File {
+        path: String,
+        size: usize,
+        preview: String,
+    },
+}
+
+/// Introspected GraphQL schema cached for a saved request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphQLSchema {
+    pub request_id: String,
+    pub url: String,
+    pub schema: serde_json::Value,  // The `__schema` object
+    pub fetched_at: i64,
+}
+
+/// Per-phase request timing. DNS, connect and TLS add up every connection
+/// the send opened and are None when it only reused pooled keep-alive ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimingBreakdown {
+    pub dns_ms: Option<u64>,
+    pub connect_ms: Option<u64>,     // TCP handshake
+    pub tls_ms: Option<u64>,         // Proxy tunnel and TLS handshake, None for plain HTTP
+    pub redirect_ms: Option<u64>,    // Hops before the final one, None without redirects
+    pub auth_retry_ms: Option<u64>,  // Digest challenge round trip before the authenticated retry
+    pub ttfb_ms: u64,                // Final request sent until its response headers arrived
+    pub download_ms: u64,
+    pub total_ms: u64,
 }
 
 /// HTTP response returned to frontend
@@ -81,10 +347,33 @@ pub struct HttpResponse {
     pub body: ResponseBody,
     pub size_bytes: usize,
     pub time_ms: u128,
+    pub timing: TimingBreakdown,
     pub content_type: Option<String>,
     pub content_encoding: Option<String>,
 }
 
+/// Stage an in-flight request has reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPhase {
+    Connecting,
+    Sending,
+    Waiting,
+    Receiving,
+    Complete,
+}
+
+/// Progress event emitted while a request is in flight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestProgress {
+    pub send_id: String,
+    pub phase: RequestPhase,
+    pub bytes_sent: u64,
+    pub total_send: Option<u64>,
+    pub bytes_received: u64,
+    pub total_receive: Option<u64>,
+}
+
 /// Transport-level errors (4xx/5xx are NOT errors, they're valid responses)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -94,23 +383,159 @@ pub enum HttpError {
     Dns { message: String },
     Tls { message: String },
     InvalidUrl { message: String },
+    Io { message: String },
+    Auth { message: String },
+    Template { message: String },
+    Script { message: String },
     Cancelled,
 }
 
+impl HttpError {
+    /// Variant name, as serialized in the `type` tag
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HttpError::Network { .. } => "Network",
+            HttpError::Timeout => "Timeout",
+            HttpError::Dns { .. } => "Dns",
+            HttpError::Tls { .. } => "Tls",
+            HttpError::InvalidUrl { .. } => "InvalidUrl",
+            HttpError::Io { .. } => "Io",
+            HttpError::Auth { .. } => "Auth",
+            HttpError::Template { .. } => "Template",
+            HttpError::Script { .. } => "Script",
+            HttpError::Cancelled => "Cancelled",
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            HttpError::Network { message }
+            | HttpError::Dns { message }
+            | HttpError::Tls { message }
+            | HttpError::InvalidUrl { message }
+            | HttpError::Io { message }
+            | HttpError::Auth { message }
+            | HttpError::Template { message }
+            | HttpError::Script { message } => Some(message),
+            HttpError::Timeout | HttpError::Cancelled => None,
+        }
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "{}: {}", self.kind(), message),
+            None => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
 /// Tagged enum result - no invalid states
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "status")]
 pub enum SendResult {
     Success { 
-        request_id: String, 
-        response: HttpResponse 
+        send_id: String, 
+        response: Box<HttpResponse>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        scripts: Vec<ScriptReport>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        assertions: Vec<AssertionResult>,
     },
     Failed { 
-        request_id: String, 
-        error: HttpError 
+        send_id: String, 
+        error: HttpError,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        scripts: Vec<ScriptReport>,  // Set when the pre-request script stopped the send
     },
 }
 
+/// Options for running a collection or folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunnerOptions {
+    #[serde(default)]
+    pub delay_ms: u64,  // Pause between requests
+    #[serde(default = "default_iterations")]
+    pub iterations: u32,
+    #[serde(default)]
+    pub stop_on_failure: bool,
+    #[serde(default)]
+    pub data: Option<RunData>,  // One iteration per row, overrides `iterations`
+}
+
+fn default_iterations() -> u32 {
+    1
+}
+
+/// Data file whose rows are fed to a run as request-local variables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunData {
+    pub format: DataFormat,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    Csv,
+    Json,  // Array of objects
+}
+
+/// Outcome of one iteration (data row) of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunIterationResult {
+    pub iteration: u32,
+    pub values: Vec<String>,  // Row values in `RunReport::columns` order
+    pub passed: bool,
+    pub requests_passed: usize,
+    pub requests_failed: usize,
+}
+
+/// Outcome of one request in a collection run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRequestResult {
+    pub iteration: u32,  // 1-based
+    pub request_id: String,
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub time_ms: Option<u64>,
+    pub passed: bool,  // Response received, scripts ran cleanly and all assertions passed
+    pub assertions_passed: usize,
+    pub assertions_failed: usize,
+    pub error: Option<String>,
+}
+
+/// Progress event emitted after each request of a collection run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunProgress {
+    pub run_id: String,
+    pub completed: usize,
+    pub total: usize,  // Requests times iterations
+    pub result: RunRequestResult,
+}
+
+/// Summary of a collection run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub run_id: String,
+    pub collection_id: String,
+    pub folder_id: Option<String>,
+    pub iterations: u32,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+    pub stopped_on_failure: bool,
+    pub started_at: i64,  // Unix ms
+    pub duration_ms: u64,
+    pub results: Vec<RunRequestResult>,
+    pub columns: Vec<String>,  // Data file columns, empty without data
+    pub iteration_results: Vec<RunIterationResult>,
+}
+
 /// Database models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Setting {
@@ -173,10 +598,156 @@ pub struct HistoryItem {
     pub request_headers: String,
     pub request_params: String,
     pub request_body: Option<String>,
-    pub response_status: u16,
-    pub response_headers: String,
-    pub response_time_ms: u64,
-    pub response_size_bytes: usize,
+    // Response section, None when the send failed
+    pub response_status: Option<u16>,
+    pub response_headers: Option<String>,
+    pub response_time_ms: Option<u64>,
+    pub response_timing: Option<String>,  // JSON TimingBreakdown
+    pub response_size_bytes: Option<usize>,
+    pub assertion_results: Option<String>,  // JSON Vec<AssertionResult>
+    // HttpError of a failed send
+    pub error_kind: Option<String>,  // HttpError variant, e.g. "Timeout"
+    pub error_message: Option<String>,
+    pub created_at: i64,
+}
+
+/// History row with the stored request and response bodies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub item: HistoryItem,
+    pub request_config: Option<String>,  // JSON RequestConfig with credentials redacted
+    pub response_body_preview: Option<String>,
+    pub response_body_full: Option<String>,
+    pub response_body_type: Option<String>,  // "text", "binary", "truncated" or "file"
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+}
+
+/// Outcome of replaying a history entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub result: SendResult,
+    pub diff: Option<ResponseDiff>,  // When asked for and both sends got a response
+}
+
+/// Differences between a stored response and the replayed one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseDiff {
+    pub previous_status: u16,
+    pub status: u16,
+    pub previous_time_ms: u64,
+    pub time_ms: u64,
+    pub headers_added: Vec<KeyValue>,
+    pub headers_removed: Vec<KeyValue>,
+    pub headers_changed: Vec<HeaderChange>,
+    pub body_changed: bool,
+    pub body_diff: Option<String>,  // Unified diff, JSON bodies pretty-printed first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderChange {
+    pub name: String,
+    pub previous: String,
+    pub current: String,
+}
+
+/// History filter on whether the send got a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryOutcome {
+    Success,
+    Failed,
+}
+
+/// History search; every set field narrows the result
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub methods: Vec<String>,  // Any of these, case-insensitive
+    pub status_min: Option<u16>,  // Inclusive; failed sends have no status
+    pub status_max: Option<u16>,
+    pub url_contains: Option<String>,  // Case-insensitive substring
+    pub url_pattern: Option<String>,  // Regex
+    pub since: Option<i64>,  // Unix ms, inclusive
+    pub until: Option<i64>,  // Unix ms, exclusive
+    pub min_time_ms: Option<u64>,  // Slower than or as slow as
+    pub max_time_ms: Option<u64>,
+    pub request_id: Option<String>,  // Linked saved request
+    pub outcome: Option<HistoryOutcome>,
+    pub text: Option<String>,  // Full-text search over URL and bodies
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// One send of a saved request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelinePoint {
+    pub history_id: String,
+    pub created_at: i64,
+    pub status: Option<u16>,  // None when the send failed
+    pub time_ms: Option<u64>,
+    pub error_kind: Option<String>,
+    pub failed: bool,  // Send failed or 5xx response
+}
+
+/// Sends of a saved request within one UTC day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    pub day_start: i64,  // Unix ms
+    pub count: usize,
+    pub failures: usize,
+    pub avg_time_ms: Option<u64>,
+    pub max_time_ms: Option<u64>,
+}
+
+/// History of a saved request with status and latency trend data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestTimeline {
+    pub request_id: String,
+    pub points: Vec<TimelinePoint>,  // Oldest first
+    pub daily: Vec<TimelineBucket>,
+    pub total: usize,
+    pub failures: usize,
+    pub p50_time_ms: Option<u64>,
+    pub p95_time_ms: Option<u64>,
+    pub failing_since: Option<i64>,  // First of the latest unbroken run of failures
+    pub last_success_at: Option<i64>,
+}
+
+/// History retention limits per workspace, from the `history_*` settings;
+/// 0 disables a limit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HistoryRetention {
+    pub max_entries: u64,
+    pub max_age_days: u64,
+    pub max_bytes: u64,  // Stored request/response text and response files
+}
+
+/// Space used by stored history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryUsage {
+    pub entries: i64,
+    pub bytes: i64,  // Stored request/response text and response files
+    pub file_bytes: i64,  // Response bodies streamed to disk
+    pub oldest_at: Option<i64>,
+    pub newest_at: Option<i64>,
+    pub database_bytes: i64,  // Whole database file, all tables
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cookie {
+    pub id: String,
+    pub workspace_id: String,
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    pub expires_at: Option<i64>,  // Unix ms, None for session cookies
+    pub secure: bool,
+    pub http_only: bool,
+    pub host_only: bool,
+    pub same_site: Option<String>,
     pub created_at: i64,
 }
 
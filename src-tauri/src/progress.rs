@@ -0,0 +1,174 @@
+use crate::models::{RequestPhase, RequestProgress};
+use bytes::Bytes;
+use http_body::{Body as HttpBody, Frame, SizeHint};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const UPLOAD_FRAME_SIZE: usize = 64 * 1024; // 64KB
+
+/// Callback receiving progress for in-flight requests
+pub type ProgressCallback = Arc<dyn Fn(RequestProgress) + Send + Sync>;
+
+/// Tracks the progress of one request and forwards throttled updates
+pub struct ProgressTracker {
+    callback: Option<ProgressCallback>,
+    state: Mutex<(RequestProgress, Instant)>,
+    body_sent_at: Mutex<Option<Instant>>,  // When the current hop's body was written
+}
+
+impl ProgressTracker {
+    pub fn new(send_id: &str, callback: Option<ProgressCallback>) -> Self {
+        let progress = RequestProgress {
+            send_id: send_id.to_string(),
+            phase: RequestPhase::Connecting,
+            bytes_sent: 0,
+            total_send: None,
+            bytes_received: 0,
+            total_receive: None,
+        };
+
+        Self {
+            callback,
+            state: Mutex::new((progress, Instant::now())),
+            body_sent_at: Mutex::new(None),
+        }
+    }
+
+    /// Note that the request body has been written, kept from the first call
+    fn mark_body_sent(&self) {
+        let mut sent_at = self.body_sent_at.lock().unwrap_or_else(|e| e.into_inner());
+        sent_at.get_or_insert_with(Instant::now);
+    }
+
+    /// When the streamed body of the last request finished sending; resets
+    /// it for the next one. None for bodies that were not streamed.
+    pub fn take_body_sent_at(&self) -> Option<Instant> {
+        self.body_sent_at.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Apply `update` and notify the callback. Byte counters are throttled,
+    /// phase changes are always delivered.
+    pub fn update(&self, update: impl FnOnce(&mut RequestProgress)) {
+        let Some(callback) = &self.callback else {
+            return;
+        };
+
+        let snapshot = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let (progress, last_emit) = &mut *state;
+            let phase = progress.phase;
+            update(progress);
+
+            if progress.phase == phase && last_emit.elapsed() < PROGRESS_INTERVAL {
+                return;
+            }
+            *last_emit = Instant::now();
+            progress.clone()
+        };
+
+        callback(snapshot);
+    }
+}
+
+/// Request body wrapper that reports upload progress as hyper pulls data.
+/// Large frames are split so progress advances smoothly.
+pub struct ProgressBody<B> {
+    inner: B,
+    pending: Bytes,
+    sent: u64,
+    total: Option<u64>,
+    tracker: Arc<ProgressTracker>,
+}
+
+impl<B> ProgressBody<B>
+where
+    B: HttpBody,
+{
+    pub fn new(inner: B, tracker: Arc<ProgressTracker>) -> Self {
+        let total = inner.size_hint().exact();
+        tracker.update(|p| {
+            p.phase = RequestPhase::Sending;
+            p.total_send = total;
+        });
+
+        Self {
+            inner,
+            pending: Bytes::new(),
+            sent: 0,
+            total,
+            tracker,
+        }
+    }
+
+    fn emit(&mut self, mut data: Bytes) -> Frame<Bytes> {
+        if data.len() > UPLOAD_FRAME_SIZE {
+            self.pending = data.split_off(UPLOAD_FRAME_SIZE);
+        }
+        let sent = data.len() as u64;
+        self.sent += sent;
+        // hyper may stop polling once Content-Length bytes are written
+        let done = self.total == Some(self.sent);
+        if done {
+            self.tracker.mark_body_sent();
+        }
+        self.tracker.update(|p| {
+            p.bytes_sent += sent;
+            if done {
+                p.phase = RequestPhase::Waiting;
+            }
+        });
+        Frame::data(data)
+    }
+}
+
+impl<B> HttpBody for ProgressBody<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            return Poll::Ready(Some(Ok(self.emit(pending))));
+        }
+
+        match Pin::new(&mut self.inner).poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                Ok(data) => Poll::Ready(Some(Ok(self.emit(data)))),
+                Err(frame) => Poll::Ready(Some(Ok(frame))),
+            },
+            Poll::Ready(None) => {
+                self.tracker.mark_body_sent();
+                self.tracker.update(|p| {
+                    if p.phase == RequestPhase::Sending {
+                        p.phase = RequestPhase::Waiting;
+                    }
+                });
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.pending.is_empty() && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let mut hint = self.inner.size_hint();
+        let pending = self.pending.len() as u64;
+        if let Some(upper) = hint.upper() {
+            hint.set_upper(upper + pending);
+        }
+        hint.set_lower(hint.lower() + pending);
+        hint
+    }
+}
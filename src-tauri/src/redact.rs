@@ -0,0 +1,274 @@
+use crate::models::*;
+use reqwest::Url;
+
+/// Replacement for redacted values in persisted history
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers that always carry credentials
+const ALWAYS_SENSITIVE: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+/// Shorter secrets are left alone, masking them would mangle unrelated text
+const MIN_SECRET_LEN: usize = 4;
+
+/// Masks credentials before a request/response pair is written to history
+pub struct Redactor {
+    secrets: Vec<String>,
+    sensitive_names: Vec<String>,
+}
+
+impl Redactor {
+    /// `secrets` are literal values to mask anywhere; `sensitive_names` are
+    /// header, param and field names (case-insensitive) whose values are masked
+    pub fn new(secrets: Vec<String>, sensitive_names: &[String]) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|s| s.len() >= MIN_SECRET_LEN)
+            .collect();
+        // Longest first so a secret containing another is masked whole
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        secrets.dedup();
+
+        let mut names: Vec<String> = sensitive_names
+            .iter()
+            .map(|n| n.trim().to_ascii_lowercase())
+            .filter(|n| !n.is_empty())
+            .collect();
+        names.extend(ALWAYS_SENSITIVE.iter().map(|n| n.to_string()));
+
+        Self {
+            secrets,
+            sensitive_names: names,
+        }
+    }
+
+    fn is_sensitive(&self, name: &str) -> bool {
+        let name = name.trim().to_ascii_lowercase();
+        self.sensitive_names.contains(&name)
+    }
+
+    /// Mask every occurrence of a secret value
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), REDACTED);
+            }
+        }
+        text
+    }
+
+    /// Mask sensitive pairs by name and secret values everywhere else
+    pub fn redact_pairs(&self, pairs: &[KeyValue]) -> Vec<KeyValue> {
+        pairs
+            .iter()
+            .map(|kv| KeyValue {
+                key: kv.key.clone(),
+                value: if self.is_sensitive(&kv.key) && !is_template(&kv.value) {
+                    REDACTED.to_string()
+                } else {
+                    self.redact_text(&kv.value)
+                },
+                enabled: kv.enabled,
+            })
+            .collect()
+    }
+
+    /// Mask sensitive query parameters and secret values in a URL
+    pub fn redact_url(&self, url: &str) -> String {
+        let redacted = match Url::parse(url) {
+            Ok(mut parsed) if parsed.query().is_some() => {
+                let pairs: Vec<(String, String)> = parsed
+                    .query_pairs()
+                    .map(|(k, v)| {
+                        let v = if self.is_sensitive(&k) { REDACTED.to_string() } else { v.into_owned() };
+                        (k.into_owned(), v)
+                    })
+                    .collect();
+                parsed.query_pairs_mut().clear().extend_pairs(pairs);
+                parsed.to_string()
+            }
+            _ => url.to_string(),
+        };
+        self.redact_text(&redacted)
+    }
+
+    fn redact_json(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(k, v)| {
+                        let v = if self.is_sensitive(k) && !v.is_object() && !v.is_array() {
+                            serde_json::Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact_json(v)
+                        };
+                        (k.clone(), v)
+                    })
+                    .collect(),
+            ),
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|v| self.redact_json(v)).collect())
+            }
+            serde_json::Value::String(s) => serde_json::Value::String(self.redact_text(s)),
+            other => other.clone(),
+        }
+    }
+
+    /// Copy of the request with credentials masked, as stored in history
+    pub fn redact_request(&self, config: &RequestConfig) -> RequestConfig {
+        let mut redacted = config.clone();
+        redacted.url = self.redact_url(&config.url);
+        redacted.headers = self.redact_pairs(&config.headers);
+        redacted.params = self.redact_pairs(&config.params);
+        redacted.auth = None;
+        // Proxy credentials are dropped, also from a user:pass@ URL
+        redacted.proxy = config.proxy.as_ref().map(|proxy| ProxyConfig {
+            url: match Url::parse(&proxy.url) {
+                Ok(mut url) => {
+                    let _ = url.set_username("");
+                    let _ = url.set_password(None);
+                    url.to_string()
+                }
+                Err(_) => self.redact_text(&proxy.url),
+            },
+            username: None,
+            password: None,
+            ..proxy.clone()
+        });
+
+        redacted.body = match &config.body {
+            RequestBody::Raw { mime, content } => RequestBody::Raw {
+                mime: mime.clone(),
+                content: self.redact_text(content),
+            },
+            RequestBody::Json { value } => RequestBody::Json {
+                value: self.redact_json(value),
+            },
+            RequestBody::FormUrlEncoded { fields } => RequestBody::FormUrlEncoded {
+                fields: self.redact_pairs(fields),
+            },
+            RequestBody::Multipart { fields } => RequestBody::Multipart {
+                fields: fields
+                    .iter()
+                    .map(|f| MultipartField {
+                        value: if self.is_sensitive(&f.key) {
+                            REDACTED.to_string()
+                        } else {
+                            self.redact_text(&f.value)
+                        },
+                        ..f.clone()
+                    })
+                    .collect(),
+            },
+            RequestBody::GraphQL { query, variables, operation_name } => RequestBody::GraphQL {
+                query: self.redact_text(query),
+                variables: variables.as_ref().map(|v| self.redact_json(v)),
+                operation_name: operation_name.clone(),
+            },
+            other => other.clone(),
+        };
+
+        redacted
+    }
+}
+
+/// Whether a value only fills in `{{...}}` placeholders, like
+/// `Bearer {{token}}` or `session={{sid}}`, so it holds no credential itself
+fn is_template(value: &str) -> bool {
+    let mut literal = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            return false;
+        };
+        literal.push_str(&rest[..start]);
+        literal.push('\0');
+        rest = &rest[start + end + 2..];
+    }
+    literal.push_str(rest);
+
+    let placeholders = |s: &str| !s.is_empty() && s.chars().all(|c| c == '\0');
+    let literal = literal.trim();
+    // An auth scheme followed by placeholders
+    let scheme = match literal.split_once(' ') {
+        Some((scheme, value)) => {
+            !scheme.is_empty()
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && placeholders(value.trim_start())
+        }
+        None => placeholders(literal),
+    };
+    // Cookie names each followed by a placeholder
+    let cookies = literal.split(';').all(|pair| {
+        matches!(pair.trim().split_once('='), Some((name, value))
+            if !name.is_empty() && !name.contains('\0') && placeholders(value))
+    });
+    scheme || cookies
+}
+
+/// Literal credential values carried by an auth config
+pub fn auth_secrets(auth: &AuthConfig) -> Vec<String> {
+    match auth {
+        AuthConfig::None => Vec::new(),
+        AuthConfig::Basic { password, .. } | AuthConfig::Digest { password, .. } => vec![password.clone()],
+        AuthConfig::Bearer { token } => vec![token.clone()],
+        AuthConfig::ApiKey { value, .. } => vec![value.clone()],
+        AuthConfig::AwsSigV4 { secret_key, session_token, .. } => {
+            let mut secrets = vec![secret_key.clone()];
+            secrets.extend(session_token.clone());
+            secrets
+        }
+        AuthConfig::OAuth2(oauth) => [
+            oauth.client_secret.clone(),
+            oauth.password.clone(),
+            oauth.refresh_token.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(key: &str, value: &str) -> KeyValue {
+        KeyValue { key: key.to_string(), value: value.to_string(), enabled: true }
+    }
+
+    fn redacted_values(headers: &[KeyValue]) -> Vec<String> {
+        Redactor::new(Vec::new(), &[])
+            .redact_pairs(headers)
+            .into_iter()
+            .map(|h| h.value)
+            .collect()
+    }
+
+    #[test]
+    fn templated_credentials_are_kept() {
+        let headers = [
+            header("Authorization", "Bearer {{token}}"),
+            header("Authorization", "{{auth_header}}"),
+            header("Cookie", "session={{sid}}; theme={{theme}}"),
+        ];
+
+        assert_eq!(
+            redacted_values(&headers),
+            vec!["Bearer {{token}}", "{{auth_header}}", "session={{sid}}; theme={{theme}}"]
+        );
+    }
+
+    #[test]
+    fn literal_credentials_are_masked() {
+        let headers = [
+            header("Authorization", "Bearer abc123"),
+            header("Authorization", "Bearer {{token}} abc123"),
+            header("Authorization", "Bearer abc{{suffix}}"),
+            header("Cookie", "session=abc123; theme={{theme}}"),
+            header("Cookie", "{{"),
+        ];
+
+        assert!(redacted_values(&headers).iter().all(|v| v == REDACTED));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{models::*, redact};
+use similar::TextDiff;
+
+/// Rebuild the request a history entry was sent with, as edited, so sending
+/// it runs its scripts and resolves its variables again. Credentials were
+/// redacted when the entry was stored, apart from templates like
+/// `Bearer {{token}}`: `auth` replaces the dropped auth config, and a request
+/// that still carries a redacted value is rejected rather than sent.
+pub fn request_config(entry: &HistoryEntry, auth: Option<AuthConfig>) -> Result<RequestConfig, String> {
+    let mut config = match entry.request_config.as_deref() {
+        Some(stored) => serde_json::from_str::<RequestConfig>(stored)
+            .map_err(|e| format!("Stored request is invalid: {}", e))?,
+        None => legacy_config(&entry.item),
+    };
+
+    config.auth = auth;
+
+    let sent_parts = serde_json::to_string(&(&config.url, &config.headers, &config.params, &config.body))
+        .map_err(|e| e.to_string())?;
+    if sent_parts.contains(redact::REDACTED) {
+        return Err("The stored request contains redacted values, edit and send it from the request editor instead".to_string());
+    }
+
+    Ok(config)
+}
+
+/// Request of an entry stored before the full config was kept: the body type
+/// is guessed from the Content-Type header
+fn legacy_config(item: &HistoryItem) -> RequestConfig {
+    let headers: Vec<KeyValue> = serde_json::from_str(&item.request_headers).unwrap_or_default();
+    let content_type = headers
+        .iter()
+        .find(|h| h.enabled && h.key.eq_ignore_ascii_case("content-type"))
+        .map(|h| h.value.clone());
+
+    let body = match (&item.request_body, content_type) {
+        (None, _) => RequestBody::None,
+        (Some(content), Some(mime)) if mime.contains("json") => match serde_json::from_str(content) {
+            Ok(value) => RequestBody::Json { value },
+            Err(_) => RequestBody::Raw { mime, content: content.clone() },
+        },
+        (Some(content), mime) => RequestBody::Raw {
+            mime: mime.unwrap_or_else(|| "text/plain".to_string()),
+            content: content.clone(),
+        },
+    };
+
+    RequestConfig {
+        method: item.method.clone(),
+        url: item.url.clone(),
+        headers,
+        params: serde_json::from_str(&item.request_params).unwrap_or_default(),
+        body,
+        ..RequestConfig::editor_defaults()
+    }
+}
+
+/// Compare a stored response with a new one; None when the entry has no
+/// response to compare with. The new response is masked with `redactor`, as
+/// the stored one was when it was written to history.
+pub fn diff_responses(entry: &HistoryEntry, response: &HttpResponse, redactor: &redact::Redactor) -> Option<ResponseDiff> {
+    let previous_status = entry.item.response_status?;
+    let previous_headers: Vec<KeyValue> = entry
+        .item
+        .response_headers
+        .as_deref()
+        .and_then(|h| serde_json::from_str(h).ok())
+        .unwrap_or_default();
+
+    let current_headers = redactor.redact_pairs(&response.headers);
+
+    let find = |headers: &[KeyValue], name: &str| {
+        headers
+            .iter()
+            .find(|h| h.key.eq_ignore_ascii_case(name))
+            .map(|h| h.value.clone())
+    };
+
+    let mut headers_removed = Vec::new();
+    let mut headers_changed = Vec::new();
+    for previous in &previous_headers {
+        match find(&current_headers, &previous.key) {
+            None => headers_removed.push(previous.clone()),
+            // Values masked in history can't be compared
+            Some(_) if previous.value == redact::REDACTED => {}
+            Some(current) if current != previous.value => headers_changed.push(HeaderChange {
+                name: previous.key.clone(),
+                previous: previous.value.clone(),
+                current,
+            }),
+            Some(_) => {}
+        }
+    }
+    let headers_added = current_headers
+        .iter()
+        .filter(|h| find(&previous_headers, &h.key).is_none())
+        .cloned()
+        .collect();
+
+    let previous_body = entry
+        .response_body_full
+        .as_deref()
+        .or(entry.response_body_preview.as_deref())
+        .unwrap_or_default();
+    let current_body = match &response.body {
+        ResponseBody::Text { content } | ResponseBody::Truncated { content, .. } => content.as_str(),
+        ResponseBody::File { preview, .. } => preview.as_str(),
+        ResponseBody::Binary { preview_hex } => preview_hex.as_str(),
+    };
+    let current_body = redactor.redact_text(current_body);
+    let (previous_body, current_body) = (comparable(previous_body), comparable(&current_body));
+    let body_changed = previous_body != current_body;
+
+    Some(ResponseDiff {
+        previous_status,
+        status: response.status,
+        previous_time_ms: entry.item.response_time_ms.unwrap_or_default(),
+        time_ms: response.time_ms as u64,
+        headers_added,
+        headers_removed,
+        headers_changed,
+        body_changed,
+        body_diff: body_changed.then(|| {
+            TextDiff::from_lines(&previous_body, &current_body)
+                .unified_diff()
+                .header("previous", "replay")
+                .to_string()
+        }),
+    })
+}
+
+/// JSON bodies are pretty-printed so formatting differences don't show up
+fn comparable(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| body.to_string()),
+        Err(_) => body.to_string(),
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{commands, http, models::*};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Cancellation state of an active run
+#[derive(Default)]
+struct RunHandle {
+    cancelled: AtomicBool,
+    current_send: Mutex<Option<String>>,
+}
+
+lazy_static! {
+    /// Active collection runs for cancellation
+    static ref ACTIVE_RUNS: DashMap<String, Arc<RunHandle>> = DashMap::new();
+}
+
+/// Stop a run after its in-flight request, which is cancelled too
+pub fn cancel_run(run_id: &str) -> bool {
+    let Some(handle) = ACTIVE_RUNS.get(run_id).map(|h| h.clone()) else {
+        return false;
+    };
+    handle.cancelled.store(true, Ordering::SeqCst);
+    if let Some(send_id) = handle.current_send.lock().unwrap_or_else(|e| e.into_inner()).as_deref() {
+        http::cancel_request(send_id);
+    }
+    true
+}
+
+/// Saved requests of a collection (or one of its folders) in the order the
+/// tree from `get_collections_with_folders` lists them: folders first, then
+/// requests at the collection root
+async fn planned_requests(
+    workspace_id: &str,
+    collection_id: &str,
+    folder_id: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let tree = commands::get_collections_with_folders(workspace_id.to_string()).await?;
+    let collection = tree
+        .as_array()
+        .and_then(|collections| collections.iter().find(|c| c["id"] == collection_id))
+        .ok_or_else(|| format!("Collection {} not found", collection_id))?;
+
+    let folders = collection["folders"].as_array().cloned().unwrap_or_default();
+    let requests_of = |node: &Value| node["requests"].as_array().cloned().unwrap_or_default();
+
+    match folder_id {
+        Some(folder_id) => folders
+            .iter()
+            .find(|f| f["id"] == folder_id)
+            .map(requests_of)
+            .ok_or_else(|| format!("Folder {} not found", folder_id)),
+        None => {
+            let mut requests: Vec<Value> = folders.iter().flat_map(requests_of).collect();
+            requests.extend(requests_of(collection));
+            Ok(requests)
+        }
+    }
+}
+
+/// Build the config the request editor sends for a saved request. Saved
+/// bodies and auth use the editor's shape ({type: "json", content}), full
+/// `RequestBody`/`AuthConfig` values are accepted as well.
+pub fn request_config(request: &Value) -> RequestConfig {
+    let pairs = |value: &Value| -> Vec<KeyValue> {
+        serde_json::from_value::<Vec<KeyValue>>(value.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|kv| kv.enabled && !kv.key.is_empty())
+            .collect()
+    };
+
+    let body = serde_json::from_value::<RequestBody>(request["body"].clone()).unwrap_or_else(|_| {
+        let content = request["body"]["content"].as_str().unwrap_or_default().to_string();
+        match request["body"]["type"].as_str() {
+            Some("json") => match serde_json::from_str(if content.trim().is_empty() { "{}" } else { &content }) {
+                Ok(value) => RequestBody::Json { value },
+                Err(_) => RequestBody::Raw {
+                    mime: "application/json".to_string(),
+                    content,
+                },
+            },
+            Some("raw") => RequestBody::Raw {
+                mime: "text/plain".to_string(),
+                content,
+            },
+            _ => RequestBody::None,
+        }
+    });
+
+    let auth = serde_json::from_value::<AuthConfig>(request["auth"].clone()).ok().or_else(|| {
+        let field = |name: &str| request["auth"][name].as_str().unwrap_or_default().to_string();
+        match request["auth"]["type"].as_str() {
+            Some("basic") => Some(AuthConfig::Basic {
+                username: field("username"),
+                password: field("password"),
+            }),
+            Some("bearer") => Some(AuthConfig::Bearer { token: field("token") }),
+            _ => None,
+        }
+    });
+
+    RequestConfig {
+        method: request["method"].as_str().unwrap_or("GET").to_string(),
+        url: request["url"].as_str().unwrap_or_default().to_string(),
+        headers: pairs(&request["headers"]),
+        params: pairs(&request["params"]),
+        body,
+        auth: auth.filter(|a| !matches!(a, AuthConfig::None)),
+        scripts: serde_json::from_value(request["scripts"].clone()).unwrap_or_default(),
+        assertions: serde_json::from_value(request["assertions"].clone()).unwrap_or_default(),
+        ..RequestConfig::editor_defaults()
+    }
+}
+
+/// Parse a data file into column names and rows of values in column order.
+/// CSV files need a header row; JSON files hold an array of flat objects,
+/// non-string values are used as their JSON text and null as "".
+fn load_data(data: &RunData) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let (columns, rows) = match data.format {
+        DataFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::Headers)
+                .from_reader(data.content.as_bytes());
+            let columns: Vec<String> = reader
+                .headers()
+                .map_err(|e| format!("Invalid CSV: {}", e))?
+                .iter()
+                .map(str::to_string)
+                .collect();
+            let rows = reader
+                .records()
+                .map(|record| {
+                    record
+                        .map(|r| r.iter().map(str::to_string).collect())
+                        .map_err(|e| format!("Invalid CSV: {}", e))
+                })
+                .collect::<Result<Vec<Vec<String>>, String>>()?;
+            (columns, rows)
+        }
+        DataFormat::Json => {
+            let value: Value = serde_json::from_str(&data.content).map_err(|e| format!("Invalid JSON: {}", e))?;
+            let objects = value
+                .as_array()
+                .ok_or("JSON data must be an array of objects")?
+                .iter()
+                .map(|row| row.as_object().ok_or("JSON data must be an array of objects"))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut columns: Vec<String> = Vec::new();
+            for key in objects.iter().flat_map(|row| row.keys()) {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+            let rows = objects
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|column| match row.get(column) {
+                            None | Some(Value::Null) => String::new(),
+                            Some(Value::String(s)) => s.clone(),
+                            Some(other) => other.to_string(),
+                        })
+                        .collect()
+                })
+                .collect();
+            (columns, rows)
+        }
+    };
+
+    if rows.is_empty() {
+        return Err("Data file has no rows".to_string());
+    }
+    Ok((columns, rows))
+}
+
+fn summarize(iteration: u32, request: &Value, config: &RequestConfig, result: &SendResult) -> RunRequestResult {
+    let mut summary = RunRequestResult {
+        iteration,
+        request_id: request["id"].as_str().unwrap_or_default().to_string(),
+        name: request["name"].as_str().unwrap_or_default().to_string(),
+        method: config.method.clone(),
+        url: config.url.clone(),
+        status: None,
+        time_ms: None,
+        passed: false,
+        assertions_passed: 0,
+        assertions_failed: 0,
+        error: None,
+    };
+
+    match result {
+        SendResult::Success { response, scripts, assertions, .. } => {
+            summary.status = Some(response.status);
+            summary.time_ms = Some(response.time_ms as u64);
+            summary.assertions_passed = assertions.iter().filter(|a| a.passed).count();
+            summary.assertions_failed = assertions.len() - summary.assertions_passed;
+            summary.error = scripts.iter().find_map(|s| s.error.clone());
+            summary.passed = summary.assertions_failed == 0 && summary.error.is_none();
+        }
+        SendResult::Failed { error, .. } => {
+            summary.error = Some(error.to_string());
+        }
+    }
+
+    summary
+}
+
+/// Send every request of a collection or folder in order, `iterations`
+/// times or once per row of the data file. Row values are request-local
+/// variables, and variables set by scripts carry over to the following
+/// requests.
+pub async fn run_collection(
+    app: &AppHandle,
+    workspace_id: &str,
+    collection_id: &str,
+    folder_id: Option<&str>,
+    options: &RunnerOptions,
+    run_id: &str,
+) -> Result<RunReport, String> {
+    let (columns, rows) = match &options.data {
+        Some(data) => load_data(data)?,
+        None => (Vec::new(), Vec::new()),
+    };
+    let requests = planned_requests(workspace_id, collection_id, folder_id).await?;
+    let iterations = if rows.is_empty() { options.iterations.max(1) } else { rows.len() as u32 };
+    let total = requests.len() * iterations as usize;
+
+    let handle = Arc::new(RunHandle::default());
+    ACTIVE_RUNS.insert(run_id.to_string(), handle.clone());
+
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let start = Instant::now();
+    let mut results = Vec::with_capacity(total);
+    let mut stopped_on_failure = false;
+
+    'run: for iteration in 1..=iterations {
+        let locals: Vec<(String, String)> = match rows.get(iteration as usize - 1) {
+            Some(row) => columns.iter().cloned().zip(row.iter().cloned()).collect(),
+            None => Vec::new(),
+        };
+
+        for request in &requests {
+            if handle.cancelled.load(Ordering::SeqCst) {
+                break 'run;
+            }
+            if !results.is_empty() && options.delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(options.delay_ms)).await;
+            }
+
+            let config = request_config(request);
+            let send_id = uuid::Uuid::new_v4().to_string();
+            *handle.current_send.lock().unwrap_or_else(|e| e.into_inner()) = Some(send_id.clone());
+
+            let result = commands::send_with_locals(
+                app.clone(),
+                config.clone(),
+                workspace_id.to_string(),
+                Some(send_id),
+                request["id"].as_str().map(str::to_string),
+                locals.clone(),
+            )
+            .await;
+
+            *handle.current_send.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+            let summary = summarize(iteration, request, &config, &result);
+            let failed = !summary.passed;
+            results.push(summary.clone());
+
+            let _ = app.emit(
+                "runner-progress",
+                RunProgress {
+                    run_id: run_id.to_string(),
+                    completed: results.len(),
+                    total,
+                    result: summary,
+                },
+            );
+
+            if failed && options.stop_on_failure {
+                stopped_on_failure = true;
+                break 'run;
+            }
+        }
+    }
+
+    ACTIVE_RUNS.remove(run_id);
+
+    let iteration_results = (1..=iterations)
+        .filter_map(|iteration| {
+            let ran: Vec<&RunRequestResult> = results.iter().filter(|r| r.iteration == iteration).collect();
+            if ran.is_empty() {
+                return None;
+            }
+            let requests_passed = ran.iter().filter(|r| r.passed).count();
+            Some(RunIterationResult {
+                iteration,
+                values: rows.get(iteration as usize - 1).cloned().unwrap_or_default(),
+                passed: requests_passed == ran.len(),
+                requests_passed,
+                requests_failed: ran.len() - requests_passed,
+            })
+        })
+        .collect();
+
+    let passed = results.iter().filter(|r| r.passed).count();
+    Ok(RunReport {
+        run_id: run_id.to_string(),
+        collection_id: collection_id.to_string(),
+        folder_id: folder_id.map(str::to_string),
+        iterations,
+        total,
+        passed,
+        failed: results.len() - passed,
+        cancelled: handle.cancelled.load(Ordering::SeqCst),
+        stopped_on_failure,
+        started_at,
+        duration_ms: start.elapsed().as_millis() as u64,
+        results,
+        columns,
+        iteration_results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csv(content: &str) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+        load_data(&RunData { format: DataFormat::Csv, content: content.to_string() })
+    }
+
+    fn json(content: &str) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+        load_data(&RunData { format: DataFormat::Json, content: content.to_string() })
+    }
+
+    #[test]
+    fn csv_rows_follow_header() {
+        let (columns, rows) = csv(" user , id\nalice,1\nbob,2\n").unwrap();
+        assert_eq!(columns, vec!["user", "id"]);
+        assert_eq!(rows, vec![vec!["alice", "1"], vec!["bob", "2"]]);
+    }
+
+    #[test]
+    fn csv_with_only_a_header_has_no_rows() {
+        assert_eq!(csv("user,id\n").unwrap_err(), "Data file has no rows");
+    }
+
+    #[test]
+    fn csv_ragged_rows_are_rejected() {
+        let error = csv("user,id\nalice,1\nbob\n").unwrap_err();
+        assert!(error.starts_with("Invalid CSV"), "{}", error);
+    }
+
+    #[test]
+    fn json_columns_are_the_union_of_keys() {
+        let (columns, rows) = json(r#"[{"user": "alice"}, {"user": "bob", "id": 2}]"#).unwrap();
+        assert_eq!(columns, vec!["user", "id"]);
+        assert_eq!(rows, vec![vec!["alice", ""], vec!["bob", "2"]]);
+    }
+
+    #[test]
+    fn json_values_are_coerced_to_text() {
+        let (columns, rows) = json(r#"[{"n": 1.5, "b": true, "z": null, "o": {"a": 1}, "s": "x"}]"#).unwrap();
+        let value = |name: &str| rows[0][columns.iter().position(|c| c == name).unwrap()].as_str();
+        assert_eq!(value("n"), "1.5");
+        assert_eq!(value("b"), "true");
+        assert_eq!(value("z"), "");
+        assert_eq!(value("o"), r#"{"a":1}"#);
+        assert_eq!(value("s"), "x");
+    }
+
+    #[test]
+    fn json_rows_must_be_objects() {
+        assert_eq!(json(r#"[{"a": 1}, 2]"#).unwrap_err(), "JSON data must be an array of objects");
+        assert_eq!(json(r#"{"a": 1}"#).unwrap_err(), "JSON data must be an array of objects");
+        assert!(json("[").unwrap_err().starts_with("Invalid JSON"));
+        assert_eq!(json("[]").unwrap_err(), "Data file has no rows");
+    }
+}
@@ -0,0 +1,372 @@
+use crate::models::*;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Sandbox limits, scripts have no file or network access to begin with
+const MAX_OPERATIONS: u64 = 5_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024; // 16MB
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+/// Result of a script run; `variables_set` holds the values to persist
+pub struct ScriptRun {
+    pub report: ScriptReport,
+    pub variables_set: Vec<(String, String)>,
+}
+
+/// Variables and output shared between the engine callbacks
+#[derive(Default)]
+struct ScriptState {
+    variables: Vec<(String, String)>,
+    variables_set: Vec<(String, String)>,
+    logs: Vec<String>,
+}
+
+impl ScriptState {
+    fn set(&mut self, key: String, value: String) {
+        match self.variables.iter_mut().find(|(k, _)| *k == key) {
+            Some(existing) => existing.1 = value.clone(),
+            None => self.variables.push((key.clone(), value.clone())),
+        }
+        self.variables_set.retain(|(k, _)| *k != key);
+        self.variables_set.push((key, value));
+    }
+}
+
+/// Sandboxed engine exposing variables, logging and crypto helpers:
+/// `get_var(name)`, `set_var(name, value)`, `print(..)`, `sha256(data)`,
+/// `hmac_sha256(key, data)`, `base64_encode`, `base64_decode`,
+/// `parse_json(text)`, `to_json(value)`, `uuid()` and `timestamp_ms()`
+fn build_engine(state: &Rc<RefCell<ScriptState>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let logs = state.clone();
+    engine.on_print(move |text| logs.borrow_mut().logs.push(text.to_string()));
+    let logs = state.clone();
+    engine.on_debug(move |text, _, _| logs.borrow_mut().logs.push(text.to_string()));
+
+    let vars = state.clone();
+    engine.register_fn("get_var", move |name: &str| -> Dynamic {
+        vars.borrow()
+            .variables
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| Dynamic::from(v.clone()))
+            .unwrap_or(Dynamic::UNIT)
+    });
+    let vars = state.clone();
+    engine.register_fn("set_var", move |name: &str, value: Dynamic| {
+        vars.borrow_mut().set(name.to_string(), value.to_string());
+    });
+
+    engine.register_fn("sha256", |data: &str| hex::encode(Sha256::digest(data.as_bytes())));
+    engine.register_fn("hmac_sha256", |key: &str, data: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    });
+    engine.register_fn("base64_encode", |data: &str| STANDARD.encode(data));
+    engine.register_fn("base64_decode", |data: &str| -> Result<String, Box<EvalAltResult>> {
+        let bytes = STANDARD.decode(data.trim()).map_err(|e| e.to_string())?;
+        String::from_utf8(bytes).map_err(|e| e.to_string().into())
+    });
+    engine.register_fn("parse_json", |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        rhai::serde::to_dynamic(value)
+    });
+    engine.register_fn("to_json", |value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+        let value: serde_json::Value = rhai::serde::from_dynamic(&value)?;
+        Ok(value.to_string())
+    });
+    engine.register_fn("uuid", || uuid::Uuid::new_v4().to_string());
+    engine.register_fn("timestamp_ms", || chrono::Utc::now().timestamp_millis());
+
+    engine
+}
+
+fn finish(stage: ScriptStage, state: Rc<RefCell<ScriptState>>, error: Option<String>) -> ScriptRun {
+    let state = state.take();
+    ScriptRun {
+        report: ScriptReport {
+            stage,
+            logs: state.logs,
+            variables_set: state.variables_set.iter().map(|(k, _)| k.clone()).collect(),
+            error,
+        },
+        variables_set: state.variables_set,
+    }
+}
+
+/// Run a pre-request script. The script sees the request as the `request`
+/// map and may change any field; on error the request is returned unchanged.
+fn pre_request(script: &str, config: RequestConfig, variables: Vec<(String, String)>) -> (RequestConfig, ScriptRun) {
+    let state = Rc::new(RefCell::new(ScriptState { variables, ..Default::default() }));
+    let engine = build_engine(&state);
+
+    let result = (|| -> Result<RequestConfig, Box<EvalAltResult>> {
+        let mut scope = Scope::new();
+        scope.push("request", rhai::serde::to_dynamic(&config)?);
+        engine.run_with_scope(&mut scope, script)?;
+        let request = scope.get_value::<Dynamic>("request").unwrap_or_default();
+        rhai::serde::from_dynamic(&request)
+    })();
+
+    drop(engine);
+    match result {
+        Ok(updated) => (updated, finish(ScriptStage::PreRequest, state, None)),
+        Err(e) => (config, finish(ScriptStage::PreRequest, state, Some(e.to_string()))),
+    }
+}
+
+/// Response as seen by scripts: header names are lowercased and `json` is
+/// the parsed body, or `()` when the body is not JSON
+fn response_value(response: &HttpResponse) -> serde_json::Value {
+    let body = match &response.body {
+        ResponseBody::Text { content } | ResponseBody::Truncated { content, .. } => content.clone(),
+        ResponseBody::File { preview, .. } => preview.clone(),
+        ResponseBody::Binary { .. } => String::new(),
+    };
+    let headers: serde_json::Map<String, serde_json::Value> = response
+        .headers
+        .iter()
+        .map(|h| (h.key.to_ascii_lowercase(), serde_json::Value::String(h.value.clone())))
+        .collect();
+
+    serde_json::json!({
+        "status": response.status,
+        "status_text": response.status_text,
+        "headers": headers,
+        "json": serde_json::from_str::<serde_json::Value>(&body).ok(),
+        "body": body,
+        "size_bytes": response.size_bytes,
+        "time_ms": response.time_ms as u64,
+    })
+}
+
+/// Run a post-response script with read-only `request` and `response` maps
+fn post_response(
+    script: &str,
+    config: &RequestConfig,
+    response: &HttpResponse,
+    variables: Vec<(String, String)>,
+) -> ScriptRun {
+    let state = Rc::new(RefCell::new(ScriptState { variables, ..Default::default() }));
+    let engine = build_engine(&state);
+
+    let result = (|| -> Result<(), Box<EvalAltResult>> {
+        let mut scope = Scope::new();
+        scope.push_constant("request", rhai::serde::to_dynamic(config)?);
+        scope.push_constant("response", rhai::serde::to_dynamic(response_value(response))?);
+        // Compiled without the scope: the optimizer would fold the constants
+        // into the script and panic on an assignment to one of them
+        let ast = engine.compile(script)?;
+        engine.run_ast_with_scope(&mut scope, &ast)
+    })();
+
+    drop(engine);
+    finish(ScriptStage::PostResponse, state, result.err().map(|e| e.to_string()))
+}
+
+/// Scripts are CPU-bound, so run them off the async runtime
+pub async fn run_pre_request(
+    script: String,
+    config: RequestConfig,
+    variables: Vec<(String, String)>,
+) -> (RequestConfig, ScriptRun) {
+    let fallback = config.clone();
+    tokio::task::spawn_blocking(move || pre_request(&script, config, variables))
+        .await
+        .unwrap_or_else(|e| (fallback, aborted(ScriptStage::PreRequest, e)))
+}
+
+pub async fn run_post_response(
+    script: String,
+    config: RequestConfig,
+    response: HttpResponse,
+    variables: Vec<(String, String)>,
+) -> ScriptRun {
+    tokio::task::spawn_blocking(move || post_response(&script, &config, &response, variables))
+        .await
+        .unwrap_or_else(|e| aborted(ScriptStage::PostResponse, e))
+}
+
+fn aborted(stage: ScriptStage, error: tokio::task::JoinError) -> ScriptRun {
+    ScriptRun {
+        report: ScriptReport {
+            stage,
+            logs: Vec::new(),
+            variables_set: Vec::new(),
+            error: Some(format!("Script aborted: {}", error)),
+        },
+        variables_set: Vec::new(),
+    }
+}
+
+/// Whether a script has anything to run
+pub fn is_empty(script: &str) -> bool {
+    script.trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vec<(String, String)> {
+        vec![
+            ("secret".to_string(), "Jefe".to_string()),
+            ("user".to_string(), "alice".to_string()),
+        ]
+    }
+
+    fn request() -> RequestConfig {
+        RequestConfig {
+            url: "https://api.example.com/items".to_string(),
+            ..RequestConfig::editor_defaults()
+        }
+    }
+
+    fn response(body: &str) -> HttpResponse {
+        HttpResponse {
+            status: 201,
+            status_text: "Created".to_string(),
+            headers: vec![KeyValue {
+                key: "X-Request-Id".to_string(),
+                value: "req-42".to_string(),
+                enabled: true,
+            }],
+            body: ResponseBody::Text { content: body.to_string() },
+            size_bytes: body.len(),
+            time_ms: 12,
+            timing: TimingBreakdown::default(),
+            content_type: Some("application/json".to_string()),
+            content_encoding: None,
+        }
+    }
+
+    #[test]
+    fn pre_request_edits_the_request() {
+        let script = r#"
+            request.method = "POST";
+            request.url += "?page=2";
+            request.headers.push(#{ key: "X-User", value: get_var("user"), enabled: true });
+        "#;
+
+        let (config, run) = pre_request(script, request(), vars());
+
+        assert_eq!(run.report.error, None);
+        assert_eq!(config.method, "POST");
+        assert_eq!(config.url, "https://api.example.com/items?page=2");
+        assert_eq!(config.headers.len(), 1);
+        assert_eq!(config.headers[0].key, "X-User");
+        assert_eq!(config.headers[0].value, "alice");
+    }
+
+    #[test]
+    fn set_var_is_visible_to_get_var_and_reported() {
+        let script = r#"
+            set_var("count", 1);
+            set_var("count", 2);
+            set_var("name", get_var("user") + "!");
+            print(get_var("count"));
+            print(get_var("missing") == ());
+        "#;
+
+        let (_, run) = pre_request(script, request(), vars());
+
+        assert_eq!(run.report.error, None);
+        assert_eq!(run.report.logs, vec!["2", "true"]);
+        assert_eq!(run.report.variables_set, vec!["count", "name"]);
+        assert_eq!(
+            run.variables_set,
+            vec![("count".to_string(), "2".to_string()), ("name".to_string(), "alice!".to_string())]
+        );
+    }
+
+    #[test]
+    fn crypto_helpers_match_known_digests() {
+        let script = r#"
+            print(sha256("abc"));
+            // RFC 4231 test case 2
+            print(hmac_sha256(get_var("secret"), "what do ya want for nothing?"));
+            print(base64_decode(base64_encode("user:pass")));
+        "#;
+
+        let (_, run) = pre_request(script, request(), vars());
+
+        assert_eq!(run.report.error, None);
+        assert_eq!(
+            run.report.logs,
+            vec![
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+                "user:pass",
+            ]
+        );
+    }
+
+    #[test]
+    fn operation_limit_stops_an_endless_loop() {
+        let (_, run) = pre_request("loop { }", request(), vars());
+
+        let error = run.report.error.expect("the loop should be stopped");
+        assert!(error.to_lowercase().contains("operations"), "{}", error);
+    }
+
+    #[test]
+    fn failed_pre_request_script_leaves_the_request_unchanged() {
+        let script = r#"
+            request.url = "https://other.example.com";
+            set_var("token", "abc");
+            print("before");
+            throw "boom";
+        "#;
+
+        let (config, run) = pre_request(script, request(), vars());
+
+        assert_eq!(config.url, "https://api.example.com/items");
+        assert!(run.report.error.unwrap().contains("boom"));
+        assert_eq!(run.report.logs, vec!["before"]);
+        assert_eq!(run.report.stage, ScriptStage::PreRequest);
+    }
+
+    #[test]
+    fn post_response_reads_the_response_and_sets_variables() {
+        let script = r#"
+            set_var("token", response.json.data.token);
+            set_var("request_id", response.headers["x-request-id"]);
+            set_var("status", response.status);
+            set_var("url", request.url);
+        "#;
+
+        let run = post_response(script, &request(), &response(r#"{"data":{"token":"tok-1"}}"#), vars());
+
+        assert_eq!(run.report.error, None);
+        assert_eq!(run.report.stage, ScriptStage::PostResponse);
+        assert_eq!(
+            run.variables_set,
+            vec![
+                ("token".to_string(), "tok-1".to_string()),
+                ("request_id".to_string(), "req-42".to_string()),
+                ("status".to_string(), "201".to_string()),
+                ("url".to_string(), "https://api.example.com/items".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn post_response_cannot_modify_the_response() {
+        let run = post_response("response.status = 500;", &request(), &response("plain text"), vars());
+
+        let error = run.report.error.expect("assigning to the response should fail");
+        assert!(error.contains("constant"), "{}", error);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::db;
+use crate::models::SecretStatus;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use lazy_static::lazy_static;
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Placeholder returned instead of secret values; saving it keeps the stored value
+pub const SECRET_MASK: &str = "********";
+
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 600_000;
+/// Encrypted with the active key so a passphrase can be checked on unlock
+const CHECK_PLAINTEXT: &str = "curlmaster-secrets";
+
+const SOURCE_SETTING: &str = "secrets_key_source";  // "keyfile" or "passphrase"
+const SALT_SETTING: &str = "secrets_salt";
+const CHECK_SETTING: &str = "secrets_check";
+
+lazy_static! {
+    /// Key for secret variables; None while a passphrase has not been entered
+    static ref KEY: RwLock<Option<Key>> = RwLock::new(None);
+
+    /// Location of the local key file, set once at startup
+    static ref KEY_FILE: RwLock<Option<PathBuf>> = RwLock::new(None);
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    Locked,
+    WrongPassphrase,
+    Corrupt,
+    Storage(String),
+}
+
+impl std::fmt::Display for SecretError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretError::Locked => write!(f, "Secret variables are locked, enter the passphrase first"),
+            SecretError::WrongPassphrase => write!(f, "Wrong passphrase"),
+            SecretError::Corrupt => write!(f, "Secret value could not be decrypted"),
+            SecretError::Storage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+impl From<sqlx::Error> for SecretError {
+    fn from(e: sqlx::Error) -> Self {
+        SecretError::Storage(e.to_string())
+    }
+}
+
+fn current_key() -> Result<Key, SecretError> {
+    KEY.read()
+        .unwrap_or_else(|e| e.into_inner())
+        .ok_or(SecretError::Locked)
+}
+
+fn set_key(key: Option<Key>) {
+    *KEY.write().unwrap_or_else(|e| e.into_inner()) = key;
+}
+
+/// Whether a stored value was written by `encrypt`
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypt a secret value with the active key
+pub fn encrypt(plaintext: &str) -> Result<String, SecretError> {
+    seal(&current_key()?, plaintext)
+}
+
+/// Decrypt a stored secret value. Values written before encryption was
+/// enabled are returned unchanged.
+pub fn decrypt(stored: &str) -> Result<String, SecretError> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+    open(&current_key()?, stored)
+}
+
+fn seal(key: &Key, plaintext: &str) -> Result<String, SecretError> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| SecretError::Corrupt)?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(data)))
+}
+
+fn open(key: &Key, stored: &str) -> Result<String, SecretError> {
+    let data = STANDARD
+        .decode(stored.trim_start_matches(ENCRYPTED_PREFIX))
+        .map_err(|_| SecretError::Corrupt)?;
+    if data.len() < NONCE_LEN {
+        return Err(SecretError::Corrupt);
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let plaintext = ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SecretError::Corrupt)?;
+
+    String::from_utf8(plaintext).map_err(|_| SecretError::Corrupt)
+}
+
+/// Derive a key from a passphrase; PBKDF2 is slow on purpose, so run it off the runtime
+async fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, SecretError> {
+    let passphrase = passphrase.to_string();
+    let salt = salt.to_vec();
+
+    tokio::task::spawn_blocking(move || {
+        let mut key = Key::default();
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, PBKDF2_ROUNDS, &mut key);
+        key
+    })
+    .await
+    .map_err(|e| SecretError::Storage(e.to_string()))
+}
+
+/// Read the local key file, creating it with a random key on first use
+async fn load_key_file() -> Result<Key, SecretError> {
+    let path = KEY_FILE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| SecretError::Storage("Key file location not set".to_string()))?;
+    let io_error = |e: std::io::Error| SecretError::Storage(format!("Key file {}: {}", path.display(), e));
+
+    match tokio::fs::read(&path).await {
+        Ok(bytes) if bytes.len() == 32 => Ok(*Key::from_slice(&bytes)),
+        Ok(_) => Err(SecretError::Storage(format!("Key file {} is invalid", path.display()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+            }
+            tokio::fs::write(&path, key.as_slice()).await.map_err(io_error)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).await;
+            }
+            Ok(key)
+        }
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+async fn uses_passphrase() -> Result<bool, SecretError> {
+    Ok(db::get_setting(SOURCE_SETTING)
+        .await?
+        .map(|s| s.value == "passphrase")
+        .unwrap_or(false))
+}
+
+/// Load the key at startup. With a passphrase configured, secrets stay
+/// locked until `unlock` is called.
+pub async fn init(key_file: PathBuf) -> Result<(), SecretError> {
+    *KEY_FILE.write().unwrap_or_else(|e| e.into_inner()) = Some(key_file);
+    reload().await
+}
+
+/// Re-read the key source, e.g. after settings were reset
+pub async fn reload() -> Result<(), SecretError> {
+    if uses_passphrase().await? {
+        set_key(None);
+        return Ok(());
+    }
+
+    set_key(Some(load_key_file().await?));
+    encrypt_plaintext_secrets().await
+}
+
+/// Unlock secrets with the configured passphrase
+pub async fn unlock(passphrase: &str) -> Result<(), SecretError> {
+    let salt = db::get_setting(SALT_SETTING)
+        .await?
+        .and_then(|s| STANDARD.decode(s.value).ok())
+        .ok_or_else(|| SecretError::Storage("No passphrase configured".to_string()))?;
+    let check = db::get_setting(CHECK_SETTING).await?.map(|s| s.value).unwrap_or_default();
+
+    let key = derive_key(passphrase, &salt).await?;
+    if open(&key, &check).ok().as_deref() != Some(CHECK_PLAINTEXT) {
+        return Err(SecretError::WrongPassphrase);
+    }
+
+    set_key(Some(key));
+    encrypt_plaintext_secrets().await
+}
+
+/// Switch to a passphrase-derived key, or back to the key file with None.
+/// All stored secrets are re-encrypted with the new key.
+pub async fn set_passphrase(passphrase: Option<&str>) -> Result<(), SecretError> {
+    let old_key = current_key()?;
+
+    let (new_key, settings) = match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = derive_key(passphrase, &salt).await?;
+            let settings = vec![
+                (SOURCE_SETTING, "passphrase".to_string()),
+                (SALT_SETTING, STANDARD.encode(salt)),
+                (CHECK_SETTING, seal(&key, CHECK_PLAINTEXT)?),
+            ];
+            (key, settings)
+        }
+        None => {
+            let settings = vec![
+                (SOURCE_SETTING, "keyfile".to_string()),
+                (SALT_SETTING, String::new()),
+                (CHECK_SETTING, String::new()),
+            ];
+            (load_key_file().await?, settings)
+        }
+    };
+
+    let mut updated = Vec::new();
+    for (table, column, rowid, stored) in db::get_secret_values().await? {
+        let plaintext = if is_encrypted(&stored) { open(&old_key, &stored)? } else { stored };
+        updated.push((table, column, rowid, seal(&new_key, &plaintext)?));
+    }
+    // Values and key settings commit together; the new key is only used once both are stored
+    db::update_secret_values(&updated, &settings).await?;
+    set_key(Some(new_key));
+
+    Ok(())
+}
+
+/// Encrypt secrets stored before encryption was enabled
+async fn encrypt_plaintext_secrets() -> Result<(), SecretError> {
+    let mut updated = Vec::new();
+    for (table, column, rowid, stored) in db::get_secret_values().await? {
+        if !is_encrypted(&stored) {
+            updated.push((table, column, rowid, encrypt(&stored)?));
+        }
+    }
+    db::update_secret_values(&updated, &[]).await?;
+    Ok(())
+}
+
+/// Current key source and lock state
+pub async fn status() -> Result<SecretStatus, SecretError> {
+    let passphrase = uses_passphrase().await?;
+    Ok(SecretStatus {
+        key_source: if passphrase { "passphrase" } else { "keyfile" }.to_string(),
+        unlocked: current_key().is_ok(),
+    })
+}
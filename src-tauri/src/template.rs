@@ -0,0 +1,611 @@
+use crate::models::{AuthConfig, KeyValue, RequestBody, RequestConfig};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::{BTreeSet, HashMap};
+
+/// Resolve `{{...}}` templates in every string field of the request; fails
+/// listing every unresolved variable, cycle or bad filter
+pub fn substitute_request(config: &mut RequestConfig, variables: &[(String, String)]) -> Result<(), String> {
+    let mut resolver = TemplateResolver::new(variables);
+    let r = &mut resolver;
+
+    config.method = r.render(&config.method);
+    config.url = r.render(&config.url);
+    render_pairs(r, &mut config.headers);
+    render_pairs(r, &mut config.params);
+
+    match &mut config.body {
+        RequestBody::None => {}
+        RequestBody::Raw { mime, content } => {
+            *mime = r.render(mime);
+            *content = r.render(content);
+        }
+        RequestBody::Json { value } => r.render_json(value),
+        RequestBody::FormUrlEncoded { fields } => render_pairs(r, fields),
+        RequestBody::Multipart { fields } => {
+            for field in fields.iter_mut().filter(|f| f.enabled) {
+                field.key = r.render(&field.key);
+                field.value = r.render(&field.value);
+                render_option(r, &mut field.file_path);
+                render_option(r, &mut field.filename);
+                render_option(r, &mut field.content_type);
+            }
+        }
+        RequestBody::File { path, content_type } => {
+            *path = r.render(path);
+            render_option(r, content_type);
+        }
+        RequestBody::GraphQL { query, variables, operation_name } => {
+            *query = r.render(query);
+            if let Some(vars) = variables {
+                r.render_json(vars);
+            }
+            render_option(r, operation_name);
+        }
+    }
+
+    match &mut config.auth {
+        None | Some(AuthConfig::None) => {}
+        Some(AuthConfig::Basic { username, password }) | Some(AuthConfig::Digest { username, password }) => {
+            *username = r.render(username);
+            *password = r.render(password);
+        }
+        Some(AuthConfig::Bearer { token }) => *token = r.render(token),
+        Some(AuthConfig::ApiKey { key, value, .. }) => {
+            *key = r.render(key);
+            *value = r.render(value);
+        }
+        Some(AuthConfig::AwsSigV4 { access_key, secret_key, session_token, region, service }) => {
+            *access_key = r.render(access_key);
+            *secret_key = r.render(secret_key);
+            render_option(r, session_token);
+            *region = r.render(region);
+            *service = r.render(service);
+        }
+        Some(AuthConfig::OAuth2(oauth)) => {
+            oauth.token_url = r.render(&oauth.token_url);
+            render_option(r, &mut oauth.auth_url);
+            render_option(r, &mut oauth.redirect_uri);
+            oauth.client_id = r.render(&oauth.client_id);
+            render_option(r, &mut oauth.client_secret);
+            render_option(r, &mut oauth.scope);
+            render_option(r, &mut oauth.username);
+            render_option(r, &mut oauth.password);
+            render_option(r, &mut oauth.refresh_token);
+        }
+    }
+
+    if let Some(proxy) = &mut config.proxy {
+        proxy.url = r.render(&proxy.url);
+        render_option(r, &mut proxy.username);
+        render_option(r, &mut proxy.password);
+        for host in &mut proxy.bypass {
+            *host = r.render(host);
+        }
+    }
+
+    resolver.finish()
+}
+
+/// Disabled pairs aren't sent, so they may reference undefined variables
+fn render_pairs(resolver: &mut TemplateResolver, pairs: &mut [KeyValue]) {
+    for pair in pairs.iter_mut().filter(|p| p.enabled) {
+        pair.key = resolver.render(&pair.key);
+        pair.value = resolver.render(&pair.value);
+    }
+}
+
+fn render_option(resolver: &mut TemplateResolver, value: &mut Option<String>) {
+    if let Some(value) = value {
+        *value = resolver.render(value);
+    }
+}
+
+/// Resolves `{{name}}` placeholders against a set of variables.
+///
+/// Variable values may themselves contain placeholders and are resolved
+/// recursively, independent of the order variables were defined in. Names
+/// starting with `$` are dynamic values generated on every use, and any
+/// placeholder can be piped through filters: `{{token | base64}}`.
+///
+/// Unresolved names and reference cycles don't stop rendering; they are
+/// collected so one error can report every problem in the request.
+pub struct TemplateResolver {
+    variables: HashMap<String, String>,
+    resolved: HashMap<String, String>,
+    unresolved: BTreeSet<String>,
+    errors: Vec<String>,
+}
+
+impl TemplateResolver {
+    pub fn new(variables: &[(String, String)]) -> Self {
+        Self {
+            variables: variables.iter().cloned().collect(),
+            resolved: HashMap::new(),
+            unresolved: BTreeSet::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Render every placeholder in `input`. Placeholders that fail to resolve
+    /// are left in place and reported by `finish`.
+    pub fn render(&mut self, input: &str) -> String {
+        self.render_with(input, &mut Vec::new())
+    }
+
+    /// Render the string keys and values of a JSON document
+    pub fn render_json(&mut self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => *s = self.render(s),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.render_json(v)),
+            serde_json::Value::Object(map) => {
+                let entries = std::mem::take(map);
+                for (key, mut v) in entries {
+                    self.render_json(&mut v);
+                    map.insert(self.render(&key), v);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Report unresolved variables, cycles and bad filters seen while rendering
+    pub fn finish(self) -> Result<(), String> {
+        let mut problems = self.errors;
+        if !self.unresolved.is_empty() {
+            let names: Vec<String> = self.unresolved.into_iter().collect();
+            problems.insert(0, format!("Unresolved variables: {}", names.join(", ")));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    fn render_with(&mut self, input: &str, stack: &mut Vec<String>) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + 2 + len + 2];
+            let expression = &rest[start + 2..start + 2 + len];
+
+            output.push_str(&rest[..start]);
+            match self.evaluate(expression, stack) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(placeholder),
+            }
+            rest = &rest[start + placeholder.len()..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+
+    /// Evaluate `name | filter | ...`, None when it can't be resolved
+    fn evaluate(&mut self, expression: &str, stack: &mut Vec<String>) -> Option<String> {
+        let mut parts = expression.split('|').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut value = if let Some(dynamic) = name.strip_prefix('$') {
+            match dynamic_value(dynamic) {
+                Some(value) => value,
+                None => {
+                    self.unresolved.insert(name.to_string());
+                    return None;
+                }
+            }
+        } else {
+            self.resolve_variable(name, stack)?
+        };
+
+        for filter in parts {
+            value = match apply_filter(filter, &value) {
+                Ok(value) => value,
+                Err(message) => {
+                    self.errors.push(message);
+                    return None;
+                }
+            };
+        }
+
+        Some(value)
+    }
+
+    fn resolve_variable(&mut self, name: &str, stack: &mut Vec<String>) -> Option<String> {
+        if let Some(value) = self.resolved.get(name) {
+            return Some(value.clone());
+        }
+
+        if stack.iter().any(|n| n == name) {
+            let mut cycle = stack.clone();
+            cycle.push(name.to_string());
+            self.errors.push(format!("Variable cycle: {}", cycle.join(" -> ")));
+            return None;
+        }
+
+        let Some(raw) = self.variables.get(name).cloned() else {
+            self.unresolved.insert(name.to_string());
+            return None;
+        };
+
+        stack.push(name.to_string());
+        let errors_before = self.errors.len() + self.unresolved.len();
+        let value = self.render_with(&raw, stack);
+        stack.pop();
+
+        // Only cache clean results so later references report the same problem
+        if self.errors.len() + self.unresolved.len() == errors_before {
+            self.resolved.insert(name.to_string(), value.clone());
+        }
+        Some(value)
+    }
+}
+
+/// Built-in `{{$name}}` values
+fn dynamic_value(name: &str) -> Option<String> {
+    let now = chrono::Utc::now();
+    let value = match name {
+        "uuid" | "guid" | "randomUUID" => uuid::Uuid::new_v4().to_string(),
+        "timestamp" => now.timestamp().to_string(),
+        "timestampMs" => now.timestamp_millis().to_string(),
+        "isoTimestamp" => now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "randomInt" => (uuid::Uuid::new_v4().as_u128() % 1000).to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn apply_filter(filter: &str, value: &str) -> Result<String, String> {
+    match filter {
+        "base64" => Ok(STANDARD.encode(value)),
+        "base64decode" => STANDARD
+            .decode(value)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| "base64decode: value is not valid base64 text".to_string()),
+        "urlencode" => Ok(url_encode(value)),
+        "urldecode" => Ok(url_decode(value)),
+        "upper" => Ok(value.to_uppercase()),
+        "lower" => Ok(value.to_lowercase()),
+        "trim" => Ok(value.trim().to_string()),
+        _ => Err(format!("Unknown filter: {}", filter)),
+    }
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+fn url_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push(high * 16 + low);
+                    i += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ApiKeyPlacement, MultipartField, OAuth2Config, OAuth2Grant, ProxyConfig, RequestScripts};
+    use serde_json::json;
+
+    fn vars() -> Vec<(String, String)> {
+        [
+            ("host", "api.example.com"),
+            ("base", "https://{{host}}/v1"),
+            ("user", "alice"),
+            ("pass", "s3cret"),
+            ("token", "tok-123"),
+            ("dir", "/tmp/uploads"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    fn kv(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            enabled: true,
+        }
+    }
+
+    fn request(body: RequestBody, auth: Option<AuthConfig>) -> RequestConfig {
+        RequestConfig {
+            method: "POST".to_string(),
+            url: "{{base}}/items".to_string(),
+            headers: vec![kv("X-User", "{{user}}")],
+            params: vec![kv("q", "{{user}}")],
+            body,
+            auth,
+            timeout_ms: 1000,
+            ignore_tls: false,
+            follow_redirects: true,
+            proxy: None,
+            max_redirects: None,
+            stream_to_file: false,
+            scripts: RequestScripts::default(),
+            assertions: Vec::new(),
+        }
+    }
+
+    fn render(input: &str) -> Result<String, String> {
+        let mut resolver = TemplateResolver::new(&vars());
+        let output = resolver.render(input);
+        resolver.finish().map(|_| output)
+    }
+
+    #[test]
+    fn resolves_nested_variables_regardless_of_order() {
+        let variables = vec![
+            ("url".to_string(), "{{scheme}}://{{host}}".to_string()),
+            ("host".to_string(), "{{name}}.test".to_string()),
+            ("scheme".to_string(), "https".to_string()),
+            ("name".to_string(), "api".to_string()),
+        ];
+        let mut resolver = TemplateResolver::new(&variables);
+        assert_eq!(resolver.render("{{url}}/x"), "https://api.test/x");
+        assert!(resolver.finish().is_ok());
+    }
+
+    #[test]
+    fn reports_every_unresolved_variable() {
+        let err = render("{{missing}}/{{user}}/{{other}}/{{$nope}}").unwrap_err();
+        assert_eq!(err, "Unresolved variables: $nope, missing, other");
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let variables = vec![
+            ("a".to_string(), "{{b}}".to_string()),
+            ("b".to_string(), "{{a}}".to_string()),
+        ];
+        let mut resolver = TemplateResolver::new(&variables);
+        resolver.render("{{a}}");
+        let err = resolver.finish().unwrap_err();
+        assert!(err.contains("Variable cycle: a -> b -> a"), "{}", err);
+    }
+
+    #[test]
+    fn applies_filters_and_dynamic_values() {
+        assert_eq!(render("{{ user | upper }}").unwrap(), "ALICE");
+        assert_eq!(render("{{user | base64}}").unwrap(), "YWxpY2U=");
+        assert_eq!(render("{{base | urlencode}}").unwrap(), "https%3A%2F%2Fapi.example.com%2Fv1");
+        assert_eq!(render("{{ user | base64 | base64decode }}").unwrap(), "alice");
+        assert!(render("{{user | shout}}").unwrap_err().contains("Unknown filter: shout"));
+
+        let uuid = render("{{$uuid}}").unwrap();
+        assert!(uuid::Uuid::parse_str(&uuid).is_ok());
+        assert!(render("{{$timestamp}}").unwrap().parse::<i64>().is_ok());
+        assert!(render("{{$randomInt}}").unwrap().parse::<u32>().unwrap() < 1000);
+    }
+
+    #[test]
+    fn leaves_text_without_placeholders_alone() {
+        assert_eq!(render("plain {text} }} {{").unwrap(), "plain {text} }} {{");
+    }
+
+    #[test]
+    fn substitutes_url_headers_and_params() {
+        let mut config = request(RequestBody::None, None);
+        substitute_request(&mut config, &vars()).unwrap();
+        assert_eq!(config.url, "https://api.example.com/v1/items");
+        assert_eq!(config.headers[0].value, "alice");
+        assert_eq!(config.params[0].value, "alice");
+    }
+
+    #[test]
+    fn substitutes_raw_json_and_graphql_bodies() {
+        let mut config = request(
+            RequestBody::Json { value: json!({ "{{user}}": ["{{token}}", 1, { "p": "{{pass}}" }] }) },
+            None,
+        );
+        substitute_request(&mut config, &vars()).unwrap();
+        let RequestBody::Json { value } = &config.body else { panic!() };
+        assert_eq!(value, &json!({ "alice": ["tok-123", 1, { "p": "s3cret" }] }));
+
+        let mut config = request(
+            RequestBody::GraphQL {
+                query: "query { user(name: \"{{user}}\") { id } }".to_string(),
+                variables: Some(json!({ "token": "{{token}}" })),
+                operation_name: None,
+            },
+            None,
+        );
+        substitute_request(&mut config, &vars()).unwrap();
+        let RequestBody::GraphQL { query, variables, .. } = &config.body else { panic!() };
+        assert_eq!(query, "query { user(name: \"alice\") { id } }");
+        assert_eq!(variables, &Some(json!({ "token": "tok-123" })));
+    }
+
+    #[test]
+    fn substitutes_form_fields() {
+        let mut config = request(
+            RequestBody::FormUrlEncoded { fields: vec![kv("{{user}}", "{{pass}}")] },
+            None,
+        );
+        substitute_request(&mut config, &vars()).unwrap();
+        let RequestBody::FormUrlEncoded { fields } = &config.body else { panic!() };
+        assert_eq!((fields[0].key.as_str(), fields[0].value.as_str()), ("alice", "s3cret"));
+    }
+
+    #[test]
+    fn substitutes_multipart_fields_and_files() {
+        let mut config = request(
+            RequestBody::Multipart {
+                fields: vec![MultipartField {
+                    key: "{{user}}".to_string(),
+                    value: "{{token}}".to_string(),
+                    enabled: true,
+                    file_path: Some("{{dir}}/a.png".to_string()),
+                    filename: Some("{{user}}.png".to_string()),
+                    content_type: Some("image/png".to_string()),
+                }],
+            },
+            None,
+        );
+        substitute_request(&mut config, &vars()).unwrap();
+        let RequestBody::Multipart { fields } = &config.body else { panic!() };
+        assert_eq!(fields[0].key, "alice");
+        assert_eq!(fields[0].value, "tok-123");
+        assert_eq!(fields[0].file_path.as_deref(), Some("/tmp/uploads/a.png"));
+        assert_eq!(fields[0].filename.as_deref(), Some("alice.png"));
+
+        let mut config = request(
+            RequestBody::File { path: "{{dir}}/body.bin".to_string(), content_type: None },
+            None,
+        );
+        substitute_request(&mut config, &vars()).unwrap();
+        let RequestBody::File { path, .. } = &config.body else { panic!() };
+        assert_eq!(path, "/tmp/uploads/body.bin");
+    }
+
+    #[test]
+    fn substitutes_basic_and_bearer_auth() {
+        let basic = AuthConfig::Basic { username: "{{user}}".to_string(), password: "{{pass}}".to_string() };
+        let mut config = request(RequestBody::None, Some(basic));
+        substitute_request(&mut config, &vars()).unwrap();
+        let Some(AuthConfig::Basic { username, password }) = &config.auth else { panic!() };
+        assert_eq!((username.as_str(), password.as_str()), ("alice", "s3cret"));
+
+        let bearer = AuthConfig::Bearer { token: "{{token}}".to_string() };
+        let mut config = request(RequestBody::None, Some(bearer));
+        substitute_request(&mut config, &vars()).unwrap();
+        let Some(AuthConfig::Bearer { token }) = &config.auth else { panic!() };
+        assert_eq!(token, "tok-123");
+    }
+
+    #[test]
+    fn substitutes_other_auth_modes() {
+        let api_key = AuthConfig::ApiKey {
+            key: "X-{{user}}".to_string(),
+            value: "{{token}}".to_string(),
+            placement: ApiKeyPlacement::Header,
+        };
+        let mut config = request(RequestBody::None, Some(api_key));
+        substitute_request(&mut config, &vars()).unwrap();
+        let Some(AuthConfig::ApiKey { key, value, .. }) = &config.auth else { panic!() };
+        assert_eq!((key.as_str(), value.as_str()), ("X-alice", "tok-123"));
+
+        let aws = AuthConfig::AwsSigV4 {
+            access_key: "{{user}}".to_string(),
+            secret_key: "{{pass}}".to_string(),
+            session_token: Some("{{token}}".to_string()),
+            region: "us-east-1".to_string(),
+            service: "execute-api".to_string(),
+        };
+        let mut config = request(RequestBody::None, Some(aws));
+        substitute_request(&mut config, &vars()).unwrap();
+        let Some(AuthConfig::AwsSigV4 { access_key, secret_key, session_token, .. }) = &config.auth else {
+            panic!()
+        };
+        assert_eq!(access_key, "alice");
+        assert_eq!(secret_key, "s3cret");
+        assert_eq!(session_token.as_deref(), Some("tok-123"));
+
+        let oauth = AuthConfig::OAuth2(OAuth2Config {
+            grant_type: OAuth2Grant::Password,
+            token_url: "{{base}}/token".to_string(),
+            auth_url: None,
+            redirect_uri: None,
+            client_id: "{{user}}-app".to_string(),
+            client_secret: Some("{{token}}".to_string()),
+            scope: None,
+            username: Some("{{user}}".to_string()),
+            password: Some("{{pass}}".to_string()),
+            refresh_token: None,
+            credentials_in_body: false,
+        });
+        let mut config = request(RequestBody::None, Some(oauth));
+        substitute_request(&mut config, &vars()).unwrap();
+        let Some(AuthConfig::OAuth2(oauth)) = &config.auth else { panic!() };
+        assert_eq!(oauth.token_url, "https://api.example.com/v1/token");
+        assert_eq!(oauth.client_id, "alice-app");
+        assert_eq!(oauth.client_secret.as_deref(), Some("tok-123"));
+        assert_eq!(oauth.password.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn substitutes_proxy_settings() {
+        let mut config = request(RequestBody::None, None);
+        config.proxy = Some(ProxyConfig {
+            enabled: true,
+            url: "http://{{host}}:3128".to_string(),
+            username: Some("{{user}}".to_string()),
+            password: Some("{{pass}}".to_string()),
+            bypass: vec!["{{host}}".to_string()],
+        });
+        substitute_request(&mut config, &vars()).unwrap();
+        let proxy = config.proxy.unwrap();
+        assert_eq!(proxy.url, "http://api.example.com:3128");
+        assert_eq!(proxy.password.as_deref(), Some("s3cret"));
+        assert_eq!(proxy.bypass, vec!["api.example.com".to_string()]);
+    }
+
+    #[test]
+    fn unresolved_variables_in_any_field_fail_the_request() {
+        let bearer = AuthConfig::Bearer { token: "{{missing_token}}".to_string() };
+        let mut config = request(
+            RequestBody::FormUrlEncoded { fields: vec![kv("a", "{{missing_field}}")] },
+            Some(bearer),
+        );
+        let err = substitute_request(&mut config, &vars()).unwrap_err();
+        assert_eq!(err, "Unresolved variables: missing_field, missing_token");
+    }
+
+    #[test]
+    fn ignores_disabled_fields() {
+        let mut config = request(
+            RequestBody::FormUrlEncoded {
+                fields: vec![KeyValue {
+                    key: "a".to_string(),
+                    value: "{{missing}}".to_string(),
+                    enabled: false,
+                }],
+            },
+            None,
+        );
+        config.headers.push(KeyValue {
+            key: "X-Off".to_string(),
+            value: "{{missing}}".to_string(),
+            enabled: false,
+        });
+        assert!(substitute_request(&mut config, &vars()).is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+tokio::task_local! {
+    /// Phase durations for the request currently being sent on this task
+    static PHASES: Arc<Mutex<ConnectionPhases>>;
+}
+
+/// Connection phases observed while sending a request, summed over the
+/// connections it opened (redirects may open several). All stay `None` when
+/// the request went out on a pooled keep-alive connection.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConnectionPhases {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,  // TCP handshake
+    pub tls: Option<Duration>,      // Proxy tunnel and TLS handshake
+    lookups_started: usize,
+    lookups_finished: usize,
+}
+
+impl ConnectionPhases {
+    /// Run `future` while recording DNS and connect times into `phases`
+    pub async fn record<F: Future>(phases: Arc<Mutex<ConnectionPhases>>, future: F) -> F::Output {
+        PHASES.scope(phases, future).await
+    }
+
+    /// Time spent setting up connections, all phases together
+    pub fn setup(&self) -> Duration {
+        self.dns.unwrap_or_default() + self.connect.unwrap_or_default() + self.tls.unwrap_or_default()
+    }
+}
+
+/// Apply `update` to the phases of the request running on the current task.
+/// Connections finished in the background by the pool have no task-local and
+/// are ignored, since they don't belong to the request being timed.
+fn update_phases(update: impl FnOnce(&mut ConnectionPhases)) {
+    let _ = PHASES.try_with(|phases| {
+        if let Ok(mut phases) = phases.lock() {
+            update(&mut phases);
+        }
+    });
+}
+
+/// Phases of the request running on the current task, empty outside of one
+fn current_phases() -> ConnectionPhases {
+    PHASES
+        .try_with(|phases| phases.lock().map(|p| *p).unwrap_or_default())
+        .unwrap_or_default()
+}
+
+/// DNS resolver that records lookup duration
+pub struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let start = Instant::now();
+            update_phases(|p| p.lookups_started += 1);
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await;
+            let elapsed = start.elapsed();
+
+            update_phases(|p| {
+                p.lookups_finished += 1;
+                p.dns = Some(p.dns.unwrap_or_default() + elapsed);
+            });
+            let addrs: Vec<_> = addrs?.collect();
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connector layer that records how long the TCP handshake of a new
+/// connection took, and the proxy tunnel and TLS handshake after it
+#[derive(Clone)]
+pub struct ConnectTimingLayer;
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTimingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTimingService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct ConnectTimingService<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for ConnectTimingService<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let mut connecting = Box::pin(self.inner.call(request));
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let dns_before = current_phases().dns.unwrap_or_default();
+            // The connector has no hooks between its steps, so they're told
+            // apart by poll: the TCP handshake starts in the poll that finishes
+            // DNS (the first one for IP hosts), and the poll after that runs
+            // once the socket is connected, starting any tunnel and TLS.
+            let mut tcp_started = false;
+            let mut after_tcp_polled = false;
+            // When the TCP handshake finished, if connecting went on after it
+            let mut tcp_done = None;
+
+            let result = std::future::poll_fn(|cx| {
+                let after_tcp = tcp_started && !after_tcp_polled;
+                let polled_at = Instant::now();
+                let poll = connecting.as_mut().poll(cx);
+
+                if after_tcp {
+                    after_tcp_polled = true;
+                    if poll.is_pending() {
+                        tcp_done = Some(polled_at);
+                    }
+                }
+                let phases = current_phases();
+                if phases.lookups_started == phases.lookups_finished {
+                    tcp_started = true;
+                }
+                poll
+            })
+            .await;
+
+            if result.is_ok() {
+                let end = Instant::now();
+                let dns = current_phases().dns.unwrap_or_default().saturating_sub(dns_before);
+                let (connect, tls) = match tcp_done {
+                    Some(at) => (at - start, Some(end - at)),
+                    None => (end - start, None),
+                };
+                update_phases(|p| {
+                    p.connect = Some(p.connect.unwrap_or_default() + connect.saturating_sub(dns));
+                    if let Some(tls) = tls {
+                        p.tls = Some(p.tls.unwrap_or_default() + tls);
+                    }
+                });
+            }
+
+            result
+        })
+    }
+}
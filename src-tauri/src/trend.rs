@@ -0,0 +1,130 @@
+use crate::models::*;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Summarize the sends of a saved request: per-day buckets, latency
+/// percentiles and since when the request has been failing
+pub fn request_timeline(request_id: &str, points: Vec<TimelinePoint>) -> RequestTimeline {
+    let mut daily: Vec<TimelineBucket> = Vec::new();
+    let mut day_times: Vec<Vec<u64>> = Vec::new();
+    for point in &points {
+        let day_start = point.created_at - point.created_at.rem_euclid(DAY_MS);
+        if daily.last().map(|b| b.day_start) != Some(day_start) {
+            daily.push(TimelineBucket {
+                day_start,
+                count: 0,
+                failures: 0,
+                avg_time_ms: None,
+                max_time_ms: None,
+            });
+            day_times.push(Vec::new());
+        }
+        let bucket = daily.last_mut().expect("bucket pushed above");
+        bucket.count += 1;
+        bucket.failures += point.failed as usize;
+        day_times.last_mut().expect("bucket pushed above").extend(point.time_ms);
+    }
+    for (bucket, times) in daily.iter_mut().zip(&day_times) {
+        if !times.is_empty() {
+            bucket.avg_time_ms = Some(times.iter().sum::<u64>() / times.len() as u64);
+            bucket.max_time_ms = times.iter().max().copied();
+        }
+    }
+
+    let mut times: Vec<u64> = points.iter().filter_map(|p| p.time_ms).collect();
+    times.sort_unstable();
+
+    let failing_run = points.iter().rev().take_while(|p| p.failed).count();
+    let failing_since = (failing_run > 0).then(|| points[points.len() - failing_run].created_at);
+    let last_success_at = points.iter().rev().find(|p| !p.failed).map(|p| p.created_at);
+
+    RequestTimeline {
+        request_id: request_id.to_string(),
+        total: points.len(),
+        failures: points.iter().filter(|p| p.failed).count(),
+        p50_time_ms: percentile(&times, 50),
+        p95_time_ms: percentile(&times, 95),
+        failing_since,
+        last_success_at,
+        daily,
+        points,
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], percent: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(created_at: i64, time_ms: Option<u64>, failed: bool) -> TimelinePoint {
+        TimelinePoint {
+            history_id: created_at.to_string(),
+            created_at,
+            status: time_ms.map(|_| if failed { 500 } else { 200 }),
+            time_ms,
+            error_kind: time_ms.is_none().then(|| "Timeout".to_string()),
+            failed,
+        }
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        assert_eq!(percentile(&[], 50), None);
+        assert_eq!(percentile(&[7], 95), Some(7));
+        let sorted: Vec<u64> = (1..=20).collect();
+        assert_eq!(percentile(&sorted, 50), Some(10));
+        assert_eq!(percentile(&sorted, 95), Some(19));
+        assert_eq!(percentile(&sorted, 100), Some(20));
+        assert_eq!(percentile(&[10, 20, 30], 50), Some(20));
+        assert_eq!(percentile(&[10, 20, 30], 95), Some(30));
+    }
+
+    #[test]
+    fn timeline_percentiles_skip_failed_sends_without_time() {
+        let points = vec![point(1, Some(300), false), point(2, None, true), point(3, Some(100), false)];
+        let timeline = request_timeline("r", points);
+        assert_eq!(timeline.p50_time_ms, Some(100));
+        assert_eq!(timeline.p95_time_ms, Some(300));
+    }
+
+    #[test]
+    fn sends_are_bucketed_by_utc_day() {
+        let points = vec![
+            point(DAY_MS - 1, Some(100), false),
+            point(DAY_MS, Some(200), true),
+            point(2 * DAY_MS - 1, Some(400), false),
+            point(2 * DAY_MS, None, true),
+        ];
+        let timeline = request_timeline("r", points);
+
+        let days: Vec<(i64, usize, usize)> = timeline.daily.iter().map(|b| (b.day_start, b.count, b.failures)).collect();
+        assert_eq!(days, vec![(0, 1, 0), (DAY_MS, 2, 1), (2 * DAY_MS, 1, 1)]);
+        assert_eq!(timeline.daily[1].avg_time_ms, Some(300));
+        assert_eq!(timeline.daily[1].max_time_ms, Some(400));
+        assert_eq!(timeline.daily[2].avg_time_ms, None);
+    }
+
+    #[test]
+    fn failing_since_is_the_start_of_the_current_failure_run() {
+        let points = vec![point(1, None, true), point(2, Some(100), false), point(3, None, true), point(4, Some(90), true)];
+        let timeline = request_timeline("r", points);
+        assert_eq!(timeline.failing_since, Some(3));
+        assert_eq!(timeline.last_success_at, Some(2));
+        assert_eq!(timeline.failures, 3);
+
+        let recovered = request_timeline("r", vec![point(1, None, true), point(2, Some(100), false)]);
+        assert_eq!(recovered.failing_since, None);
+
+        let never_passed = request_timeline("r", vec![point(1, None, true), point(2, None, true)]);
+        assert_eq!(never_passed.failing_since, Some(1));
+        assert_eq!(never_passed.last_success_at, None);
+    }
+}